@@ -11,165 +11,4598 @@
 //! println!("{}", term.style("bold_red_on_black", "Hello!"));
 //! ```
 
-//! ```rust
-//! fn main() { println!("test") }
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::io::{self, Stderr, Stdout, Write, BufWriter};
+use std::sync::{Arc, Mutex, MutexGuard};
+#[cfg(all(unix, feature = "sigwinch"))]
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use crossterm::{
+    cursor, execute, queue,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    style::{self, Attribute},
+    terminal,
+};
+
+use unicode_segmentation::UnicodeSegmentation;
+
+pub use crossterm::style::Color;
+
+/// Struct for .size()
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Size {
+    pub x: u16,
+    pub y: u16,
+}
+
+impl Size {
+    /// Splits the area into side-by-side `(x, y, w, h)` columns sized by relative `weights`.
+    ///
+    /// The columns always tile the full width exactly, with no gaps or overlaps.
+    ///
+    /// ```rust
+    /// use blessingrs::Size;
+    /// let size = Size { x: 80, y: 24 };
+    /// assert_eq!(size.split_horizontal(&[1, 2]), vec![(0, 0, 27, 24), (27, 0, 53, 24)]);
+    /// ```
+    pub fn split_horizontal(&self, weights: &[u16]) -> Vec<(u16, u16, u16, u16)> {
+        split_span(self.x, weights)
+            .into_iter()
+            .map(|(start, len)| (start, 0, len, self.y))
+            .collect()
+    }
+
+    /// Splits the area into stacked `(x, y, w, h)` rows sized by relative `weights`.
+    ///
+    /// ```rust
+    /// use blessingrs::Size;
+    /// let size = Size { x: 80, y: 10 };
+    /// assert_eq!(size.split_vertical(&[1, 1, 1]), vec![(0, 0, 80, 3), (0, 3, 80, 4), (0, 7, 80, 3)]);
+    /// ```
+    pub fn split_vertical(&self, weights: &[u16]) -> Vec<(u16, u16, u16, u16)> {
+        split_span(self.y, weights)
+            .into_iter()
+            .map(|(start, len)| (0, start, self.x, len))
+            .collect()
+    }
+}
+
+/// A cell on screen, as a named alternative to bare `(x, y)` tuples.
+///
+/// ```rust
+/// use blessingrs::Position;
+/// let origin = Position::new(2, 1);
+/// assert_eq!(origin.offset(3, -5), Position { x: 5, y: 0 });
+/// assert_eq!(origin.offset(0, i32::MAX), Position { x: 2, y: u16::MAX });
+/// assert_eq!(<(u16, u16)>::from(origin), (2, 1));
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Position {
+    pub x: u16,
+    pub y: u16,
+}
+
+impl Position {
+    pub fn new(x: u16, y: u16) -> Self {
+        Self { x, y }
+    }
+
+    /// Returns this position moved by `dx, dy`, stopping at `0` and `u16::MAX`.
+    pub fn offset(&self, dx: i32, dy: i32) -> Self {
+        let shift = |v: u16, d: i32| (v as i32).saturating_add(d).clamp(0, u16::MAX as i32) as u16;
+        Self { x: shift(self.x, dx), y: shift(self.y, dy) }
+    }
+}
+
+impl From<(u16, u16)> for Position {
+    fn from((x, y): (u16, u16)) -> Self {
+        Self { x, y }
+    }
+}
+
+impl From<Position> for (u16, u16) {
+    fn from(pos: Position) -> Self {
+        (pos.x, pos.y)
+    }
+}
+
+/// Divides `total` cells into `(start, len)` runs proportional to `weights`.
+///
+/// Boundaries are rounded from the running weight total, so the runs always
+/// add up to `total`. All-zero weights split evenly.
+fn split_span(total: u16, weights: &[u16]) -> Vec<(u16, u16)> {
+    let even = weights.iter().all(|&w| w == 0);
+    let weight = |w: u16| if even { 1 } else { w as u64 };
+    let sum: u64 = weights.iter().map(|&w| weight(w)).sum();
+
+    let mut runs = Vec::with_capacity(weights.len());
+    let mut acc = 0;
+    let mut start = 0;
+    for &w in weights {
+        acc += weight(w);
+        let end = ((total as u64 * acc + sum / 2) / sum) as u16;
+        runs.push((start, end - start));
+        start = end;
+    }
+    runs
+}
+
+/// A screen area given either in cells or as a share of the terminal size.
+///
+/// Percentage rects are resolved against `size()` at draw time, so a dialog
+/// stays centered and proportional as the terminal resizes:
+///
+/// ```rust
+/// use blessingrs::{Rect, Size};
+/// let dialog = Rect::centered_pct(0.6, 0.4);
+/// assert_eq!(dialog.resolve(Size { x: 80, y: 24 }), (16, 7, 48, 10));
+/// assert_eq!(Rect::abs(1, 2, 3, 4).resolve(Size { x: 80, y: 24 }), (1, 2, 3, 4));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rect {
+    kind: RectKind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum RectKind {
+    Abs(u16, u16, u16, u16),
+    CenteredPct(f32, f32),
+}
+
+impl Rect {
+    /// A rect at a fixed `x, y` with a fixed `w` x `h` size.
+    pub fn abs(x: u16, y: u16, w: u16, h: u16) -> Self {
+        Self { kind: RectKind::Abs(x, y, w, h) }
+    }
+
+    /// A rect centered on screen, taking `w_frac` of the width and `h_frac` of the height.
+    ///
+    /// Fractions are clamped to `0.0..=1.0`.
+    pub fn centered_pct(w_frac: f32, h_frac: f32) -> Self {
+        Self { kind: RectKind::CenteredPct(w_frac, h_frac) }
+    }
+
+    /// Returns the `(x, y, w, h)` this rect covers on a screen of `size`.
+    ///
+    /// Percentage rects are rounded so they always fit fully on screen.
+    pub fn resolve(&self, size: Size) -> (u16, u16, u16, u16) {
+        match self.kind {
+            RectKind::Abs(x, y, w, h) => (x, y, w, h),
+            RectKind::CenteredPct(w_frac, h_frac) => {
+                let scale = |frac: f32, len: u16| ((frac.clamp(0.0, 1.0) * len as f32).round() as u16).min(len);
+                let (w, h) = (scale(w_frac, size.x), scale(h_frac, size.y));
+                ((size.x - w) / 2, (size.y - h) / 2, w, h)
+            }
+        }
+    }
+}
+
+bitflags::bitflags! {
+    /// Text attributes applied alongside colors by [`Terminal::style_with`].
+    ///
+    /// The underline styles (`CURLY_UNDERLINE` and friends) each draw an
+    /// underline of their own; `UNDERLINE` alone is a straight one. Attributes
+    /// print and parse as their spec words:
+    ///
+    /// ```rust
+    /// use blessingrs::Attrs;
+    /// let attrs = Attrs::BOLD | Attrs::UNDERLINE;
+    /// assert_eq!(attrs.to_string(), "bold_underline");
+    /// assert_eq!("underline+bold".parse::<Attrs>(), Ok(attrs));
+    /// assert_eq!(attrs.sgr(), "\x1b[1m\x1b[4m");
+    /// assert_eq!(Attrs::from_prefixes("bold_curly_underline_red"), (Attrs::BOLD | Attrs::CURLY_UNDERLINE | Attrs::UNDERLINE, "red"));
+    /// ```
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+    pub struct Attrs: u16 {
+        const BOLD = 1 << 0;
+        const DIM = 1 << 1;
+        const ITALIC = 1 << 2;
+        const UNDERLINE = 1 << 3;
+        const BLINK = 1 << 4;
+        const REVERSE = 1 << 5;
+        const HIDDEN = 1 << 6;
+        const STRIKETHROUGH = 1 << 7;
+        const DOUBLE_UNDERLINE = 1 << 8;
+        const CURLY_UNDERLINE = 1 << 9;
+        const DOTTED_UNDERLINE = 1 << 10;
+        const DASHED_UNDERLINE = 1 << 11;
+    }
+}
+
+impl Attrs {
+    /// Every underline style flag, i.e. everything that draws an underline except `UNDERLINE`.
+    const UNDERLINE_STYLES: Attrs = Attrs::DOUBLE_UNDERLINE
+        .union(Attrs::CURLY_UNDERLINE)
+        .union(Attrs::DOTTED_UNDERLINE)
+        .union(Attrs::DASHED_UNDERLINE);
+
+    /// Spec words for each attribute, in canonical order.
+    const NAMES: [(Attrs, &'static str); 12] = [
+        (Attrs::BOLD, "bold"),
+        (Attrs::DIM, "dim"),
+        (Attrs::ITALIC, "italic"),
+        (Attrs::DOUBLE_UNDERLINE, "double"),
+        (Attrs::CURLY_UNDERLINE, "curly"),
+        (Attrs::DOTTED_UNDERLINE, "dotted"),
+        (Attrs::DASHED_UNDERLINE, "dashed"),
+        (Attrs::UNDERLINE, "underline"),
+        (Attrs::BLINK, "blink"),
+        (Attrs::REVERSE, "reverse"),
+        (Attrs::HIDDEN, "hidden"),
+        (Attrs::STRIKETHROUGH, "strikethrough"),
+    ];
+
+    /// Looks up the attribute with spec word `word`, ignoring case.
+    fn from_word(word: &str) -> Option<Attrs> {
+        Self::NAMES.iter().find(|(_, name)| name.eq_ignore_ascii_case(word)).map(|&(attr, _)| attr)
+    }
+
+    /// Splits leading `_`-separated attribute words off `spec`, returning them and the rest.
+    pub fn from_prefixes(spec: &str) -> (Attrs, &str) {
+        let mut attrs = Attrs::empty();
+        let mut rest = spec;
+        while !rest.is_empty() {
+            let (word, after) = rest.split_once('_').unwrap_or((rest, ""));
+            match Attrs::from_word(word) {
+                Some(attr) => attrs |= attr,
+                None => break,
+            }
+            rest = after;
+        }
+        (attrs, rest)
+    }
+
+    /// Returns how underlined text is drawn, or `None` if it isn't underlined.
+    pub fn underline_style(&self) -> Option<UnderlineStyle> {
+        [
+            (Attrs::DOUBLE_UNDERLINE, UnderlineStyle::Double),
+            (Attrs::CURLY_UNDERLINE, UnderlineStyle::Curly),
+            (Attrs::DOTTED_UNDERLINE, UnderlineStyle::Dotted),
+            (Attrs::DASHED_UNDERLINE, UnderlineStyle::Dashed),
+            (Attrs::UNDERLINE, UnderlineStyle::Straight),
+        ]
+        .into_iter()
+        .find(|&(flag, _)| self.contains(flag))
+        .map(|(_, style)| style)
+    }
+
+    /// Spec names of the attributes that are turned on, in canonical order.
+    fn names(&self) -> Vec<&'static str> {
+        Self::NAMES.iter().filter(|&&(attr, _)| self.contains(attr)).map(|&(_, name)| name).collect()
+    }
+
+    /// Lists the crossterm attributes that are turned on.
+    fn attributes(&self) -> Vec<Attribute> {
+        let mut attributes: Vec<Attribute> = [
+            (Attrs::BOLD, Attribute::Bold),
+            (Attrs::DIM, Attribute::Dim),
+            (Attrs::ITALIC, Attribute::Italic),
+        ]
+        .into_iter()
+        .filter_map(|(attr, sgr)| self.contains(attr).then_some(sgr))
+        .collect();
+        attributes.extend(self.underline_style().map(UnderlineStyle::attribute));
+        attributes.extend(
+            [
+                (Attrs::BLINK, Attribute::SlowBlink),
+                (Attrs::REVERSE, Attribute::Reverse),
+                (Attrs::HIDDEN, Attribute::Hidden),
+                (Attrs::STRIKETHROUGH, Attribute::CrossedOut),
+            ]
+            .into_iter()
+            .filter_map(|(attr, sgr)| self.contains(attr).then_some(sgr)),
+        );
+        attributes
+    }
+
+    /// Returns the escape sequences that turn these attributes on.
+    pub fn sgr(&self) -> String {
+        self.attributes().into_iter().map(|attr| style::SetAttribute(attr).to_string()).collect()
+    }
+}
+
+impl std::fmt::Display for Attrs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.names().join("_"))
+    }
+}
+
+impl std::str::FromStr for Attrs {
+    type Err = StyleError;
+
+    /// Parses attribute words joined by `_`, `+` or `,`, e.g. `bold+underline`.
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let invalid = || StyleError::InvalidFormat { spec: spec.to_string(), reason: "Unknown attribute." };
+        spec.split(['_', '+', ','])
+            .filter(|word| !word.is_empty())
+            .try_fold(Attrs::empty(), |attrs, word| Ok(attrs | Attrs::from_word(word).ok_or_else(invalid)?))
+    }
+}
+
+/// The shape of an underline, on terminals that support more than a straight line.
+///
+/// In specs, the style's name goes before `underline`, e.g. `curly_underline_red`.
+/// Terminals without styled underlines get a straight underline instead.
+///
+/// ```rust
+/// use blessingrs::{Attrs, Style, UnderlineStyle};
+/// let squiggle = Style::from_spec("curly_underline_red").unwrap();
+/// assert_eq!(squiggle.to_spec(), "curly_underline_red");
+/// assert_eq!(Attrs::from(UnderlineStyle::Curly).underline_style(), Some(UnderlineStyle::Curly));
+/// assert_eq!(Style::new(Some(blessingrs::Color::Red), None, UnderlineStyle::Curly.into()), squiggle);
+/// assert_eq!(blessingrs::Terminal::headless().apply_style(&squiggle, "x"), "\x1b[38;5;9m\x1b[4:3mx\x1b[0m");
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UnderlineStyle {
+    #[default]
+    Straight,
+    Double,
+    Curly,
+    Dotted,
+    Dashed,
+}
+
+impl UnderlineStyle {
+    /// Maps the `n` of an `4:n` SGR parameter to a style.
+    fn from_sgr(n: u16) -> Self {
+        match n {
+            2 => UnderlineStyle::Double,
+            3 => UnderlineStyle::Curly,
+            4 => UnderlineStyle::Dotted,
+            5 => UnderlineStyle::Dashed,
+            _ => UnderlineStyle::Straight,
+        }
+    }
+
+    fn attribute(self) -> Attribute {
+        match self {
+            UnderlineStyle::Straight => Attribute::Underlined,
+            UnderlineStyle::Double => Attribute::DoubleUnderlined,
+            UnderlineStyle::Curly => Attribute::Undercurled,
+            UnderlineStyle::Dotted => Attribute::Underdotted,
+            UnderlineStyle::Dashed => Attribute::Underdashed,
+        }
+    }
+}
+
+/// Includes `UNDERLINE` alongside the style's own flag, as specs and parsed SGR do.
+impl From<UnderlineStyle> for Attrs {
+    fn from(style: UnderlineStyle) -> Attrs {
+        Attrs::UNDERLINE
+            | match style {
+                UnderlineStyle::Straight => Attrs::empty(),
+                UnderlineStyle::Double => Attrs::DOUBLE_UNDERLINE,
+                UnderlineStyle::Curly => Attrs::CURLY_UNDERLINE,
+                UnderlineStyle::Dotted => Attrs::DOTTED_UNDERLINE,
+                UnderlineStyle::Dashed => Attrs::DASHED_UNDERLINE,
+            }
+    }
+}
+
+/// Why a style spec couldn't be parsed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StyleError {
+    /// A color name that isn't recognized.
+    UnknownColor(String),
+    /// A `#` color that isn't valid `#rrggbb` hex.
+    InvalidHex(String),
+    /// A spec whose overall shape is wrong.
+    InvalidFormat { spec: String, reason: &'static str },
+}
+
+impl std::fmt::Display for StyleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StyleError::UnknownColor(name) => write!(f, "Unknown color member: {}", name),
+            StyleError::InvalidHex(hex) => write!(f, "Invalid hex color: {}", hex),
+            StyleError::InvalidFormat { spec, reason } => write!(f, "Invalid style format: {}. {}", spec, reason),
+        }
+    }
+}
+
+impl std::error::Error for StyleError {}
+
+/// A compiled style spec: optional colors plus attributes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Style {
+    fg: Option<Color>,
+    bg: Option<Color>,
+    attrs: Attrs,
+    reset: bool,
+}
+
+impl Style {
+    pub fn new(fg: Option<Color>, bg: Option<Color>, attrs: Attrs) -> Self {
+        Self { fg, bg, attrs, reset: false }
+    }
+
+    /// Returns the foreground color, if any.
+    ///
+    /// ```rust
+    /// use blessingrs::{Attrs, Color, Style};
+    /// let style = Style::from_spec("bold_red_on_blue").unwrap();
+    /// assert_eq!((style.fg(), style.bg(), style.attrs()), (Some(Color::Red), Some(Color::Blue), Attrs::BOLD));
+    /// ```
+    pub fn fg(&self) -> Option<Color> {
+        self.fg
+    }
+
+    /// Returns the background color, if any.
+    pub fn bg(&self) -> Option<Color> {
+        self.bg
+    }
+
+    /// Returns the attributes this style turns on.
+    pub fn attrs(&self) -> Attrs {
+        self.attrs
+    }
+
+    /// Layers `over` on top of this style, e.g. a selection highlight over a row's base style.
+    ///
+    /// Colors set in `over` replace this style's; colors it leaves unset are
+    /// kept. Attributes are combined, so nothing turned on in either is lost.
+    ///
+    /// ```rust
+    /// use blessingrs::Style;
+    /// let row = Style::from_spec("bold_white_on_black").unwrap();
+    /// let selected = Style::from_spec("underline_on_blue").unwrap();
+    /// assert_eq!(row.merge(selected).to_spec(), "bold_underline_white_on_blue");
+    /// assert_eq!(row.merge(Style::default()), row);
+    /// ```
+    pub fn merge(&self, over: Style) -> Self {
+        Self {
+            fg: over.fg.or(self.fg),
+            bg: over.bg.or(self.bg),
+            attrs: self.attrs | over.attrs,
+            reset: self.reset || over.reset,
+        }
+    }
+
+    /// Returns a faded version of this style, e.g. for disabled menu items.
+    ///
+    /// Turns on `dim`, and a truecolor foreground is also blended halfway
+    /// toward grey, since some terminals render `dim` weakly or not at all.
+    /// Named and palette colors are kept so the style works without truecolor.
+    ///
+    /// ```rust
+    /// use blessingrs::Style;
+    /// let item = Style::from_spec("#ff0000_on_black").unwrap();
+    /// assert_eq!(item.dimmed().to_spec(), "dim_#c04040_on_black");
+    /// assert_eq!(Style::from_spec("red").unwrap().dimmed().to_spec(), "dim_red");
+    /// assert_eq!(Style::from_spec("red").unwrap().highlighted().to_spec(), "reverse_red");
+    /// ```
+    pub fn dimmed(&self) -> Self {
+        let mut style = *self;
+        style.attrs |= Attrs::DIM;
+        if let Some(Color::Rgb { r, g, b }) = style.fg {
+            style.fg = Some(lerp_rgb((r, g, b), (128, 128, 128), 0.5));
+        }
+        style
+    }
+
+    /// Returns this style with colors swapped (`reverse`), e.g. for the selected item.
+    pub fn highlighted(&self) -> Self {
+        let mut style = *self;
+        style.attrs |= Attrs::REVERSE;
+        style
+    }
+
+    /// Returns this style with `attrs` turned on or off, e.g. `with_attr(Attrs::UNDERLINE, focused)`.
+    ///
+    /// ```rust
+    /// use blessingrs::{Color, Style};
+    /// let base = Style::from_spec("red").unwrap();
+    /// let focused = base.with_bold(true).with_fg(Color::Yellow).with_bg(Some(Color::Blue));
+    /// assert_eq!(focused.to_spec(), "bold_yellow_on_blue");
+    /// assert_eq!(focused.with_bold(false).with_bg(None).to_spec(), "yellow");
+    /// ```
+    pub fn with_attr(mut self, attrs: Attrs, on: bool) -> Self {
+        self.attrs.set(attrs, on);
+        self
+    }
+
+    /// Returns this style with `bold` turned on or off.
+    pub fn with_bold(self, on: bool) -> Self {
+        self.with_attr(Attrs::BOLD, on)
+    }
+
+    /// Returns this style with foreground `fg`.
+    pub fn with_fg(mut self, fg: Color) -> Self {
+        self.fg = Some(fg);
+        self
+    }
+
+    /// Returns this style with background `bg`, or none.
+    pub fn with_bg(mut self, bg: Option<Color>) -> Self {
+        self.bg = bg;
+        self
+    }
+
+    /// Parses a spec string: `[reset_][attr_]...foreground[_on_background]`.
+    ///
+    /// Attributes (`bold`, `dim`, `italic`, `underline`, `blink`, `reverse`,
+    /// `hidden`, `strikethrough`, and the [`UnderlineStyle`] words) can be stacked as prefixes (`bold_underline_red`) or
+    /// joined onto the foreground with `+` or `,` (`red+bold+underline`).
+    /// Both forms may be mixed; their attributes are combined, and the single
+    /// non-attribute word in the foreground is always the color.
+    ///
+    /// Colors are the basic names (`red`, `grey`, ...), their `dark` variants
+    /// (`darkred`), `default`, a 256-color index (`208`) or hex (`#ff8800`).
+    /// A foreground of `auto` picks black or white, whichever reads best on the
+    /// background, and the foreground can be left out entirely (`on_blue`).
+    /// A leading `reset_` clears any styling left active by earlier output
+    /// before applying the rest (`reset_bold_red`).
+    pub fn from_spec(style_spec: &str) -> Result<Self, StyleError> {
+        let invalid = |reason| StyleError::InvalidFormat { spec: style_spec.to_string(), reason };
+        let mut style = Style::default();
+        let mut spec = style_spec;
+
+        loop {
+            let (attrs, rest) = Attrs::from_prefixes(spec);
+            style.attrs |= attrs;
+            spec = rest;
+            match spec.split_once('_') {
+                Some((word, rest)) if word.eq_ignore_ascii_case("reset") => {
+                    style.reset = true;
+                    spec = rest;
+                }
+                _ => break,
+            }
+        }
+
+        let parts: Vec<&str> = spec.split("_on_").collect();
+
+        let fg_spec = match parts.as_slice() {
+            [bg_only] if bg_only.starts_with("on_") => {
+                style.bg = Some(parse_color(&bg_only[3..])?);
+                ""
+            }
+            [fg_spec] => fg_spec,
+            [fg_spec, bg_name] => {
+                style.bg = Some(parse_color(bg_name)?);
+                fg_spec
+            }
+            _ => return Err(invalid("Use '[bold_]fg_on_bg'.")),
+        };
+
+        let mut fg_name = None;
+        if !fg_spec.is_empty() {
+            for word in fg_spec.split(['+', ',']) {
+                if word.eq_ignore_ascii_case("reset") {
+                    style.reset = true;
+                } else if let Some(attr) = Attrs::from_word(word) {
+                    style.attrs |= attr;
+                } else if fg_name.replace(word).is_some() {
+                    return Err(invalid("Only one foreground color is allowed."));
+                }
+            }
+        }
+
+        style.fg = match (fg_name, style.bg) {
+            (Some(name), Some(bg)) if name.eq_ignore_ascii_case("auto") => Some(contrasting_color(bg)),
+            (Some(name), None) if name.eq_ignore_ascii_case("auto") => {
+                return Err(invalid("'auto' needs a background."));
+            }
+            (Some(name), _) => Some(parse_color(name)?),
+            (None, _) => None,
+        };
+
+        Ok(style)
+    }
+
+    /// Renders the style back into its canonical spec string.
+    ///
+    /// The canonical form is `[reset_][attr_]...[fg][_on_bg]` with attributes
+    /// in the order listed by [`Style::from_spec`], lowercase color names,
+    /// 256-color indexes as plain numbers and truecolor as `#rrggbb`. An `auto`
+    /// foreground is written as the color it resolved to. Parsing the result
+    /// always gives back an equal `Style`.
+    ///
+    /// ```rust
+    /// use blessingrs::Style;
+    /// let style = Style::from_spec("red+underline+bold_on_#00ff80").unwrap();
+    /// assert_eq!(style.to_spec(), "bold_underline_red_on_#00ff80");
+    /// assert_eq!(Style::from_spec(&style.to_spec()), Ok(style));
+    /// assert_eq!(Style::from_spec("on_208").unwrap().to_spec(), "on_208");
+    /// assert_eq!(Style::from_spec("reset").unwrap().to_spec(), "reset");
+    /// assert!(Style::from_spec("bold_purple").is_err());
+    /// ```
+    pub fn to_spec(&self) -> String {
+        let mut words: Vec<String> = Vec::new();
+        if self.reset {
+            words.push("reset".to_string());
+        }
+        words.extend(self.attrs.names().into_iter().map(String::from));
+
+        if let Some(fg) = self.fg {
+            words.push(color_name(fg));
+        }
+        if let Some(bg) = self.bg {
+            words.push(format!("on_{}", color_name(bg)));
+        }
+        words.join("_")
+    }
+}
+
+impl std::str::FromStr for Style {
+    type Err = StyleError;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        Style::from_spec(spec)
+    }
+}
+
+/// Writes the escapes selecting `fg`, `bg` and `attrs` to `out`.
+fn write_sgr(out: &mut impl std::fmt::Write, fg: Option<Color>, bg: Option<Color>, attrs: Attrs) -> std::fmt::Result {
+    if let Some(fg) = fg {
+        write!(out, "{}", style::SetForegroundColor(fg))?;
+    }
+    if let Some(bg) = bg {
+        write!(out, "{}", style::SetBackgroundColor(bg))?;
+    }
+    for attr in attrs.attributes() {
+        write!(out, "{}", style::SetAttribute(attr))?;
+    }
+    Ok(())
+}
+
+/// Writes the escapes that turn output styled as `from` into output styled as `to`.
+///
+/// Only what differs is emitted. Attributes that share an off switch (bold
+/// and dim, the underline styles) are turned back on after it if `to` keeps them.
+fn write_sgr_delta(out: &mut impl std::fmt::Write, from: &Style, to: &Style) -> std::fmt::Result {
+    const OFF: [(Attrs, Attribute); 7] = [
+        (Attrs::BOLD.union(Attrs::DIM), Attribute::NormalIntensity),
+        (Attrs::ITALIC, Attribute::NoItalic),
+        (Attrs::UNDERLINE.union(Attrs::UNDERLINE_STYLES), Attribute::NoUnderline),
+        (Attrs::BLINK, Attribute::NoBlink),
+        (Attrs::REVERSE, Attribute::NoReverse),
+        (Attrs::HIDDEN, Attribute::NoHidden),
+        (Attrs::STRIKETHROUGH, Attribute::NotCrossedOut),
+    ];
+
+    let mut on = to.attrs.difference(from.attrs);
+    let removed = from.attrs.difference(to.attrs);
+    for (group, off) in OFF {
+        if removed.intersects(group) {
+            write!(out, "{}", style::SetAttribute(off))?;
+            on |= to.attrs & group;
+        }
+    }
+    if to.fg != from.fg {
+        write!(out, "{}", style::SetForegroundColor(to.fg.unwrap_or(Color::Reset)))?;
+    }
+    if to.bg != from.bg {
+        write!(out, "{}", style::SetBackgroundColor(to.bg.unwrap_or(Color::Reset)))?;
+    }
+    write_sgr(out, None, None, on)
+}
+
+/// Text that's styled when formatted, returned by [`styled`].
+#[derive(Clone, Copy, Debug)]
+pub struct Styled<'a> {
+    style: Option<Style>,
+    text: &'a str,
+}
+
+/// Wraps `text` so formatting it writes it styled by `style_spec`, without allocating.
+///
+/// Unlike [`Terminal::style`], this doesn't know the terminal's capabilities,
+/// so every escape is written as-is. An invalid spec formats as the plain text.
+///
+/// ```rust
+/// use blessingrs::{styled, Terminal};
+/// let line = format!("{}: disk full", styled("bold_red", "error"));
+/// assert_eq!(line, format!("{}: disk full", Terminal::headless().style("bold_red", "error")));
+/// assert_eq!(styled("not_a_color", "plain").to_string(), "plain");
+/// ```
+pub fn styled<'a>(style_spec: &str, text: &'a str) -> Styled<'a> {
+    Styled { style: Style::from_spec(style_spec).ok(), text }
+}
+
+impl std::fmt::Display for Styled<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Some(style) = self.style else {
+            return f.write_str(self.text);
+        };
+        if style.reset {
+            write!(f, "{}", style::ResetColor)?;
+        }
+        if style.fg.is_none() && style.bg.is_none() && style.attrs.is_empty() {
+            return f.write_str(self.text);
+        }
+        write_sgr(f, style.fg, style.bg, style.attrs)?;
+        write!(f, "{}{}", self.text, style::ResetColor)
+    }
+}
+
+/// Splits styled text back into runs of plain text with the style active for each.
+///
+/// SGR sequences (`\x1b[...m`) are tracked to work out each run's style;
+/// other escape sequences are dropped. This is the inverse of `style`, so
+/// colored text can be re-wrapped without losing its colors.
+///
+/// ```rust
+/// use blessingrs::{parse_spans, Style, Terminal};
+/// let term = Terminal::headless();
+/// let text = format!("{} ok", term.style("bold_red", "err"));
+/// assert_eq!(parse_spans(&text), vec![
+///     (Style::from_spec("bold_red").unwrap(), "err".to_string()),
+///     (Style::default(), " ok".to_string()),
+/// ]);
+/// ```
+pub fn parse_spans(text: &str) -> Vec<(Style, String)> {
+    let mut spans: Vec<(Style, String)> = Vec::new();
+    let mut current = Style::default();
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            match spans.last_mut() {
+                Some((style, run)) if *style == current => run.push(c),
+                _ => spans.push((current, c.to_string())),
+            }
+            continue;
+        }
+
+        match chars.next() {
+            Some('[') => {
+                let mut params = String::new();
+                let mut terminator = None;
+                for c in chars.by_ref() {
+                    if ('\x40'..='\x7e').contains(&c) {
+                        terminator = Some(c);
+                        break;
+                    }
+                    params.push(c);
+                }
+                if terminator == Some('m') {
+                    apply_sgr(&mut current, &params);
+                }
+            }
+            Some(']') => {
+                // OSC: runs until BEL or ST (ESC \).
+                while let Some(c) = chars.next() {
+                    if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    spans
+}
+
+/// Updates `style` with the effect of one SGR parameter list, e.g. `1;38;5;9`.
+fn apply_sgr(style: &mut Style, params: &str) {
+    // Empty parameters mean 0. Each code comes with its `:` sub-parameter, as
+    // in the `4:n` underline styles ("4:0" is off).
+    let mut codes = params.split(';').map(|param| {
+        let mut parts = param.split(':').map(|part| part.parse::<u16>().unwrap_or(0));
+        let code = parts.next().unwrap_or(0);
+        match parts.next() {
+            Some(0) if code == 4 => (24, None),
+            sub => (code, sub),
+        }
+    });
+
+    while let Some((code, sub)) = codes.next() {
+        let attrs = &mut style.attrs;
+        match code {
+            0 => *style = Style::default(),
+            1 => attrs.insert(Attrs::BOLD),
+            2 => attrs.insert(Attrs::DIM),
+            3 => attrs.insert(Attrs::ITALIC),
+            4 | 21 => {
+                let underline = if code == 21 { UnderlineStyle::Double } else { UnderlineStyle::from_sgr(sub.unwrap_or(1)) };
+                attrs.remove(Attrs::UNDERLINE | Attrs::UNDERLINE_STYLES);
+                attrs.insert(Attrs::from(underline));
+            }
+            5 | 6 => attrs.insert(Attrs::BLINK),
+            7 => attrs.insert(Attrs::REVERSE),
+            8 => attrs.insert(Attrs::HIDDEN),
+            9 => attrs.insert(Attrs::STRIKETHROUGH),
+            22 => attrs.remove(Attrs::BOLD | Attrs::DIM),
+            23 => attrs.remove(Attrs::ITALIC),
+            24 => attrs.remove(Attrs::UNDERLINE | Attrs::UNDERLINE_STYLES),
+            25 => attrs.remove(Attrs::BLINK),
+            27 => attrs.remove(Attrs::REVERSE),
+            28 => attrs.remove(Attrs::HIDDEN),
+            29 => attrs.remove(Attrs::STRIKETHROUGH),
+            30..=37 => style.fg = Some(ansi_index_color((code - 30) as u8)),
+            90..=97 => style.fg = Some(ansi_index_color((code - 90 + 8) as u8)),
+            40..=47 => style.bg = Some(ansi_index_color((code - 40) as u8)),
+            100..=107 => style.bg = Some(ansi_index_color((code - 100 + 8) as u8)),
+            39 => style.fg = None,
+            49 => style.bg = None,
+            38 | 48 | 58 => {
+                let mut next = || codes.next().map(|(n, _)| n);
+                let color = match next() {
+                    Some(5) => next().map(|n| ansi_index_color(n as u8)),
+                    Some(2) => {
+                        let mut channel = || next().unwrap_or(0) as u8;
+                        Some(rgb(channel(), channel(), channel()))
+                    }
+                    _ => None,
+                };
+                match code {
+                    38 => style.fg = color.or(style.fg),
+                    48 => style.bg = color.or(style.bg),
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Parses a spec for the panicking drawing APIs.
+fn compile_spec(style_spec: &str) -> Style {
+    Style::from_spec(style_spec).unwrap_or_else(|err| panic!("{}", err))
+}
+
+fn parse_color(name: &str) -> Result<Color, StyleError> {
+    Ok(match name.to_lowercase().as_str() {
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "white" => Color::White,
+        "grey" | "gray" => Color::Grey,
+        "darkred" => Color::DarkRed,
+        "darkgreen" => Color::DarkGreen,
+        "darkyellow" => Color::DarkYellow,
+        "darkblue" => Color::DarkBlue,
+        "darkmagenta" => Color::DarkMagenta,
+        "darkcyan" => Color::DarkCyan,
+        "darkgrey" | "darkgray" => Color::DarkGrey,
+        "default" => Color::Reset,
+        hex if hex.starts_with('#') => match parse_hex(hex) {
+            Some((r, g, b)) => Color::Rgb { r, g, b },
+            None => return Err(StyleError::InvalidHex(name.to_string())),
+        },
+        index if !index.is_empty() && index.bytes().all(|b| b.is_ascii_digit()) => match index.parse() {
+            Ok(n) => Color::AnsiValue(n),
+            Err(_) => return Err(StyleError::UnknownColor(name.to_string())),
+        },
+        _ => return Err(StyleError::UnknownColor(name.to_string())),
+    })
+}
+
+/// The canonical spec name for `color`, as accepted by `parse_color`.
+fn color_name(color: Color) -> String {
+    let name = match color {
+        Color::Black => "black",
+        Color::Red => "red",
+        Color::Green => "green",
+        Color::Yellow => "yellow",
+        Color::Blue => "blue",
+        Color::Magenta => "magenta",
+        Color::Cyan => "cyan",
+        Color::White => "white",
+        Color::Grey => "grey",
+        Color::DarkRed => "darkred",
+        Color::DarkGreen => "darkgreen",
+        Color::DarkYellow => "darkyellow",
+        Color::DarkBlue => "darkblue",
+        Color::DarkMagenta => "darkmagenta",
+        Color::DarkCyan => "darkcyan",
+        Color::DarkGrey => "darkgrey",
+        Color::Reset => "default",
+        Color::AnsiValue(n) => return n.to_string(),
+        Color::Rgb { r, g, b } => return format!("#{:02x}{:02x}{:02x}", r, g, b),
+    };
+    name.to_string()
+}
+
+/// A high-level drawing operation, as recorded by [`Terminal::start_capture`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DrawOp {
+    MoveTo(u16, u16),
+    /// Text printed with an optional style; unstyled text may carry its own escapes.
+    Print(Option<Style>, String),
+    Clear,
+    /// Colors left active for following prints, from [`Terminal::set_colors`].
+    SetColors(Option<Color>, Option<Color>),
+    /// Attributes left active for following prints, from [`Terminal::set_attrs`].
+    SetAttrs(Attrs),
+    ResetStyle,
+    ShowCursor,
+    HideCursor,
+}
+
+/// Operations recorded while capturing.
+struct Capture {
+    ops: Vec<DrawOp>,
+    emit: bool,
+}
+
+/// Where a `Terminal` sends its bytes.
+enum Output {
+    Stdout(Stdout),
+    Stderr(Stderr),
+    Memory(Vec<u8>),
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Output::Stdout(out) => out.write(buf),
+            Output::Stderr(out) => out.write(buf),
+            Output::Memory(out) => out.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Output::Stdout(out) => out.flush(),
+            Output::Stderr(out) => out.flush(),
+            Output::Memory(_) => Ok(()),
+        }
+    }
+}
+
+/// Horizontal placement of text within a wider span.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+/// Returns how many terminal columns `text` occupies.
+///
+/// Text is measured per grapheme cluster, so combining marks add nothing and
+/// emoji sequences (flags, ZWJ families) count as a single wide glyph.
+///
+/// ```rust
+/// use blessingrs::display_width;
+/// assert_eq!(display_width("abc"), 3);
+/// assert_eq!(display_width("日本"), 4);
+/// assert_eq!(display_width("e\u{301}"), 1);
+/// assert_eq!(display_width("🇯🇵"), 2);
+/// assert_eq!(display_width("👨\u{200d}👩\u{200d}👧"), 2);
+/// ```
+pub fn display_width(text: &str) -> usize {
+    display_width_with(text, AmbiguousWidth::Narrow)
+}
+
+/// How wide to count Unicode "ambiguous width" characters such as `─`, `…` and arrows.
+///
+/// Most terminals draw them one column wide; terminals configured for East
+/// Asian locales often draw them two columns wide.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AmbiguousWidth {
+    #[default]
+    Narrow,
+    Wide,
+}
+
+/// Like `display_width`, counting ambiguous-width characters as `ambiguous` says.
+///
+/// ```rust
+/// use blessingrs::{display_width_with, AmbiguousWidth};
+/// assert_eq!(display_width_with("→ ok", AmbiguousWidth::Narrow), 4);
+/// assert_eq!(display_width_with("→ ok", AmbiguousWidth::Wide), 5);
+/// ```
+pub fn display_width_with(text: &str, ambiguous: AmbiguousWidth) -> usize {
+    text.graphemes(true).map(|g| grapheme_width(g, ambiguous)).sum()
+}
+
+fn grapheme_width(grapheme: &str, ambiguous: AmbiguousWidth) -> usize {
+    // Joined or variation-selected emoji render as one wide glyph.
+    if grapheme.chars().count() > 1 && grapheme.contains(['\u{200D}', '\u{FE0F}']) {
+        return 2;
+    }
+    grapheme.chars().map(|c| char_width(c, ambiguous)).max().unwrap_or(0)
+}
+
+fn char_width(c: char, ambiguous: AmbiguousWidth) -> usize {
+    if ambiguous == AmbiguousWidth::Wide && is_ambiguous_width(c) {
+        return 2;
+    }
+    match c as u32 {
+        0 => 0,
+        0x01..=0x1F | 0x7F..=0x9F => 0,
+        0x0300..=0x036F
+        | 0x1AB0..=0x1AFF
+        | 0x1DC0..=0x1DFF
+        | 0x200B..=0x200F
+        | 0x20D0..=0x20FF
+        | 0xFE00..=0xFE0F
+        | 0xFE20..=0xFE2F => 0,
+        0x1100..=0x115F
+        | 0x2E80..=0x303E
+        | 0x3041..=0x33FF
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xA000..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x1F1E6..=0x1F1FF
+        | 0x1F300..=0x1F64F
+        | 0x1F680..=0x1F6FF
+        | 0x1F900..=0x1F9FF
+        | 0x1FA70..=0x1FAFF
+        | 0x20000..=0x3FFFD => 2,
+        _ => 1,
+    }
+}
+
+/// The commonly hit East Asian Width "ambiguous" characters.
+fn is_ambiguous_width(c: char) -> bool {
+    matches!(c as u32,
+        0x00A1 | 0x00A4 | 0x00A7 | 0x00A8 | 0x00AA | 0x00AD | 0x00AE
+        | 0x00B0..=0x00B4
+        | 0x00B6..=0x00BA
+        | 0x00BC..=0x00BF
+        | 0x00D7 | 0x00F7
+        | 0x0391..=0x03A9
+        | 0x03B1..=0x03C9
+        | 0x0401 | 0x0451
+        | 0x0410..=0x044F
+        | 0x2010 | 0x2013..=0x2016
+        | 0x2018 | 0x2019 | 0x201C | 0x201D
+        | 0x2020..=0x2022
+        | 0x2024..=0x2027
+        | 0x2030 | 0x2032 | 0x2033 | 0x2035 | 0x203B | 0x203E
+        | 0x2103 | 0x2109 | 0x2113 | 0x2116 | 0x2121 | 0x2122 | 0x2126 | 0x212B
+        | 0x2160..=0x216B
+        | 0x2170..=0x2179
+        | 0x2190..=0x2199
+        | 0x21D2 | 0x21D4
+        | 0x2200..=0x22FF
+        | 0x2460..=0x24E9
+        | 0x24EB..=0x254B
+        | 0x2550..=0x2573
+        | 0x2580..=0x258F
+        | 0x2592..=0x2595
+        | 0x25A0..=0x25FF
+        | 0x2605 | 0x2606 | 0x2609 | 0x260E | 0x260F | 0x261C | 0x261E
+        | 0x2640 | 0x2642
+        | 0x2660..=0x266F
+        | 0xE000..=0xF8FF
+        | 0xFFFD
+    )
+}
+
+/// Shortens `text` to at most `width` columns, ending in `…` when cut.
+///
+/// ```rust
+/// use blessingrs::truncate;
+/// assert_eq!(truncate("Settings", 5), "Sett…");
+/// assert_eq!(truncate("Menu", 5), "Menu");
+/// ```
+pub fn truncate(text: &str, width: usize) -> String {
+    truncate_with(text, width, "…", AmbiguousWidth::Narrow)
+}
+
+/// Shortens `text` to `width` columns, making room for `ellipsis` when it has to cut.
+fn truncate_with(text: &str, width: usize, ellipsis: &str, ambiguous: AmbiguousWidth) -> String {
+    if display_width_with(text, ambiguous) <= width {
+        return text.to_string();
+    }
+
+    let ellipsis_width = display_width_with(ellipsis, ambiguous);
+    if ellipsis_width > width {
+        return String::new();
+    }
+
+    let mut out = String::new();
+    let mut used = 0;
+    for grapheme in text.graphemes(true) {
+        let w = grapheme_width(grapheme, ambiguous);
+        if used + w > width - ellipsis_width {
+            break;
+        }
+        out.push_str(grapheme);
+        used += w;
+    }
+    out.push_str(ellipsis);
+    out
+}
+
+/// Clips `text` to `width` columns, passing escape sequences through untouched.
+///
+/// Returns the clipped text and the number of columns it occupies.
+fn clip_ansi(text: &str, width: usize, ambiguous: AmbiguousWidth) -> (String, usize) {
+    let mut out = String::new();
+    let mut used = 0;
+    let mut full = false;
+    let mut rest = text;
+    while !rest.is_empty() {
+        if rest.starts_with('\x1b') {
+            let len = escape_len(rest);
+            out.push_str(&rest[..len]);
+            rest = &rest[len..];
+            continue;
+        }
+
+        let end = rest.find('\x1b').unwrap_or(rest.len());
+        for grapheme in rest[..end].graphemes(true) {
+            let w = grapheme_width(grapheme, ambiguous);
+            full |= used + w > width;
+            if !full {
+                out.push_str(grapheme);
+                used += w;
+            }
+        }
+        rest = &rest[end..];
+    }
+    (out, used)
+}
+
+/// Returns the `width` columns of `text` starting at column `start`.
+///
+/// Escape sequences are kept wherever they fall, so styling set before the
+/// window still applies inside it. A wide character cut by either edge is
+/// replaced by spaces for the half that shows.
+///
+/// ```rust
+/// use blessingrs::slice_columns;
+/// assert_eq!(slice_columns("hello world", 6, 3), "wor");
+/// assert_eq!(slice_columns("日本語", 1, 4), " 本 ");
+/// assert_eq!(slice_columns("\x1b[31mred\x1b[0m", 1, 5), "\x1b[31med\x1b[0m");
+/// ```
+pub fn slice_columns(text: &str, start: usize, width: usize) -> String {
+    slice_columns_with(text, start, width, AmbiguousWidth::Narrow)
+}
+
+fn slice_columns_with(text: &str, start: usize, width: usize, ambiguous: AmbiguousWidth) -> String {
+    let end = start.saturating_add(width);
+    let mut out = String::new();
+    let mut col = 0;
+    let mut rest = text;
+    while !rest.is_empty() {
+        if rest.starts_with('\x1b') {
+            let len = escape_len(rest);
+            out.push_str(&rest[..len]);
+            rest = &rest[len..];
+            continue;
+        }
+
+        let run = rest.find('\x1b').unwrap_or(rest.len());
+        for grapheme in rest[..run].graphemes(true) {
+            let w = grapheme_width(grapheme, ambiguous);
+            if col >= start && col + w <= end {
+                out.push_str(grapheme);
+            } else {
+                let shown = (col + w).min(end).saturating_sub(col.max(start));
+                out.push_str(&" ".repeat(shown));
+            }
+            col += w;
+        }
+        rest = &rest[run..];
+    }
+    out
+}
+
+/// Parses the `rgb:rrrr/gggg/bbbb` color in an OSC color reply.
+///
+/// Each channel has 1 to 4 hex digits and is scaled to 8 bits.
+fn parse_osc_color(reply: &[u8]) -> Option<Color> {
+    let reply = std::str::from_utf8(reply).ok()?;
+    let spec = &reply[reply.find("rgb:")? + 4..];
+    let spec = &spec[..spec.find(['\x07', '\x1b']).unwrap_or(spec.len())];
+    let mut channels = spec.split('/').map(|hex| {
+        let value = u32::from_str_radix(hex, 16).ok()?;
+        let max = (1u32 << (4 * hex.len().clamp(1, 4))) - 1;
+        Some((value * 255 / max) as u8)
+    });
+    Some(rgb(channels.next()??, channels.next()??, channels.next()??))
+}
+
+/// Returns whether `reply` holds a complete OSC sequence, ended by BEL or ST.
+fn osc_complete(reply: &[u8]) -> bool {
+    reply.windows(2).position(|pair| pair == b"\x1b]").is_some_and(|start| {
+        let body = &reply[start + 2..];
+        body.contains(&0x07) || body.windows(2).any(|pair| pair == b"\x1b\\")
+    })
+}
+
+/// Returns whether `reply` holds a primary device attributes reply (`\x1b[?...c`).
+fn device_attributes_complete(reply: &[u8]) -> bool {
+    reply.windows(3).position(|triple| triple == b"\x1b[?").is_some_and(|start| reply[start..].contains(&b'c'))
+}
+
+/// Pulls the name out of an XTVERSION reply (`\x1bP>|name version\x1b\\`).
+fn parse_xtversion(reply: &[u8]) -> Option<String> {
+    let start = reply.windows(4).position(|quad| quad == b"\x1bP>|")? + 4;
+    let len = reply[start..].windows(2).position(|pair| pair == b"\x1b\\")?;
+    let name = String::from_utf8_lossy(&reply[start..start + len]).trim().to_string();
+    (!name.is_empty()).then_some(name)
+}
+
+/// Returns where the cursor ends up after printing `text` from `pos` on a screen of `size`.
+///
+/// Escape sequences take no room. Without raw mode, `\n` also returns to the
+/// first column, as the tty translates it to `\r\n`. Text running past the
+/// right edge wraps onto the next row, and the bottom row scrolls rather than
+/// moving the cursor further down.
+fn advance_cursor(pos: (u16, u16), text: &str, size: Size, raw_mode: bool, ambiguous: AmbiguousWidth) -> (u16, u16) {
+    let (width, bottom) = (size.x.max(1) as usize, size.y.saturating_sub(1));
+    let (mut x, mut y) = (pos.0 as usize, pos.1);
+    let advance = |x: &mut usize, y: &mut u16, run: &str| {
+        let end = *x + clip_ansi(run, usize::MAX, ambiguous).1;
+        if end >= width {
+            // The cursor stays on the last column until the next glyph wraps it.
+            let wraps = (end - 1) / width;
+            *y = y.saturating_add(wraps.min(u16::MAX as usize) as u16).min(bottom);
+            *x = ((end - 1) % width + 1).min(width - 1);
+        } else {
+            *x = end;
+        }
+    };
+
+    let mut run_start = 0;
+    for (i, c) in text.char_indices().filter(|&(_, c)| c == '\n' || c == '\r') {
+        advance(&mut x, &mut y, &text[run_start..i]);
+        if c == '\n' {
+            y = y.saturating_add(1).min(bottom);
+        }
+        if c == '\r' || !raw_mode {
+            x = 0;
+        }
+        run_start = i + 1;
+    }
+    advance(&mut x, &mut y, &text[run_start..]);
+    (x as u16, y)
+}
+
+/// Returns the byte length of the escape sequence at the start of `s`.
+fn escape_len(s: &str) -> usize {
+    let bytes = s.as_bytes();
+    match bytes.get(1) {
+        // CSI: parameters up to a final byte in `@`..=`~`.
+        Some(b'[') => bytes[2..].iter().position(|b| (0x40..=0x7e).contains(b)).map_or(s.len(), |i| i + 3),
+        // OSC: up to BEL or ST.
+        Some(b']') => match bytes.iter().position(|&b| b == 0x07) {
+            Some(i) => i + 1,
+            None => s.find("\x1b\\").map_or(s.len(), |i| i + 2),
+        },
+        _ => s[1..].chars().next().map_or(1, |c| 1 + c.len_utf8()),
+    }
+}
+
+/// Formats `d` for a status display: `03:45` under an hour, `1h02m03s` from there on.
+///
+/// ```rust
+/// use blessingrs::format_duration;
+/// use std::time::Duration;
+/// assert_eq!(format_duration(Duration::from_secs(225)), "03:45");
+/// assert_eq!(format_duration(Duration::from_secs(3723)), "1h02m03s");
+/// ```
+pub fn format_duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    match secs {
+        0..3600 => format!("{:02}:{:02}", secs / 60, secs % 60),
+        _ => format!("{}h{:02}m{:02}s", secs / 3600, secs / 60 % 60, secs % 60),
+    }
+}
+
+/// Like [`format_duration`], but shows tenths of a second under a minute, e.g. `1.2s`.
+///
+/// ```rust
+/// use blessingrs::format_duration_precise;
+/// use std::time::Duration;
+/// assert_eq!(format_duration_precise(Duration::from_millis(1250)), "1.2s");
+/// assert_eq!(format_duration_precise(Duration::from_secs(75)), "01:15");
+/// ```
+pub fn format_duration_precise(d: Duration) -> String {
+    match d.as_secs() {
+        0..60 => format!("{}.{}s", d.as_secs(), d.subsec_millis() / 100),
+        _ => format_duration(d),
+    }
+}
+
+/// Returns whether the cell `mouse_x, mouse_y` lies inside the `(x, y, w, h)` rectangle.
+///
+/// ```rust
+/// use blessingrs::hit_test;
+/// assert!(hit_test((2, 2, 10, 1), 11, 2));
+/// assert!(!hit_test((2, 2, 10, 1), 12, 2));
+/// ```
+pub fn hit_test(rect: (u16, u16, u16, u16), mouse_x: u16, mouse_y: u16) -> bool {
+    let (x, y, w, h) = rect;
+    (x..x.saturating_add(w)).contains(&mouse_x) && (y..y.saturating_add(h)).contains(&mouse_y)
+}
+
+/// Word-wraps `text` into lines of at most `width` columns.
+///
+/// Existing newlines are kept, words longer than a line are broken, and
+/// blank lines come through as empty strings.
+///
+/// ```rust
+/// use blessingrs::wrap;
+/// assert_eq!(wrap("the quick brown fox", 10), vec!["the quick", "brown fox"]);
+/// assert_eq!(wrap("abcdefgh", 3), vec!["abc", "def", "gh"]);
+/// assert_eq!(wrap("a\n\nb", 5), vec!["a", "", "b"]);
+/// ```
+pub fn wrap(text: &str, width: usize) -> Vec<String> {
+    wrap_with(text, width, AmbiguousWidth::Narrow)
+}
+
+fn wrap_with(text: &str, width: usize, ambiguous: AmbiguousWidth) -> Vec<String> {
+    if width == 0 {
+        return Vec::new();
+    }
+
+    let mut lines = Vec::new();
+    for paragraph in text.split('\n') {
+        let mut line = String::new();
+        let mut used = 0;
+        for word in paragraph.split_whitespace() {
+            let word_width = display_width_with(word, ambiguous);
+            if used > 0 && used + 1 + word_width <= width {
+                line.push(' ');
+                used += 1;
+            } else if used > 0 {
+                lines.push(std::mem::take(&mut line));
+                used = 0;
+            }
+
+            for grapheme in word.graphemes(true) {
+                let w = grapheme_width(grapheme, ambiguous);
+                if used + w > width && used > 0 {
+                    lines.push(std::mem::take(&mut line));
+                    used = 0;
+                }
+                line.push_str(grapheme);
+                used += w;
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// Picks black or white, whichever contrasts best with `bg`.
+///
+/// Palette colors are resolved to their usual xterm RGB values before the
+/// perceived luminance is compared.
+///
+/// ```rust
+/// use blessingrs::{contrasting_color, Color};
+/// assert_eq!(contrasting_color(Color::Yellow), Color::Black);
+/// assert_eq!(contrasting_color(Color::DarkBlue), Color::White);
+/// assert_eq!(contrasting_color(Color::Rgb { r: 250, g: 250, b: 250 }), Color::Black);
+/// ```
+pub fn contrasting_color(bg: Color) -> Color {
+    let (r, g, b) = approx_rgb(bg);
+    let luminance = 0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32;
+    if luminance > 128.0 { Color::Black } else { Color::White }
+}
+
+/// Builds a truecolor `Color` from its red, green and blue channels.
+pub fn rgb(r: u8, g: u8, b: u8) -> Color {
+    Color::Rgb { r, g, b }
+}
+
+/// Evenly spaced stops of the viridis colormap, from dark purple to yellow.
+const VIRIDIS: [(u8, u8, u8); 9] = [
+    (68, 1, 84),
+    (71, 45, 123),
+    (59, 82, 139),
+    (44, 114, 142),
+    (33, 145, 140),
+    (40, 174, 128),
+    (94, 201, 98),
+    (173, 220, 48),
+    (253, 231, 37),
+];
+
+/// Maps `value` in `0.0..=1.0` onto a perceptually uniform ramp, for heatmaps.
+///
+/// Uses the viridis colormap, whose lightness rises steadily so values read
+/// correctly even in greyscale. Out-of-range values are clamped.
+///
+/// ```rust
+/// use blessingrs::{heat_color, rgb};
+/// assert_eq!(heat_color(0.0), rgb(68, 1, 84));
+/// assert_eq!(heat_color(1.0), rgb(253, 231, 37));
+/// assert_eq!(heat_color(7.0), heat_color(1.0));
+/// ```
+pub fn heat_color(value: f32) -> Color {
+    let value = if value.is_nan() { 0.0 } else { value.clamp(0.0, 1.0) };
+    let pos = value * (VIRIDIS.len() - 1) as f32;
+    let i = (pos as usize).min(VIRIDIS.len() - 2);
+    lerp_rgb(VIRIDIS[i], VIRIDIS[i + 1], pos - i as f32)
+}
+
+/// Picks a stable, distinct color for `key`, e.g. to color usernames consistently.
+///
+/// The same key always gives the same color, across runs and platforms. The
+/// key's hash is stepped around the hue circle by the golden ratio, so keys
+/// with nearby hashes still land on clearly different hues, and saturation
+/// and lightness stay in a band that reads well on dark backgrounds.
+///
+/// ```rust
+/// use blessingrs::color_for;
+/// assert_eq!(color_for("alice"), color_for("alice"));
+/// assert_ne!(color_for("alice"), color_for("bob"));
+/// ```
+pub fn color_for(key: &str) -> Color {
+    // FNV-1a, so the result doesn't depend on the std hasher's random seed.
+    let hash = key.bytes().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    });
+
+    const GOLDEN_RATIO_CONJUGATE: f64 = 0.618_033_988_749_895;
+    let hue = ((hash >> 40) as f64 * GOLDEN_RATIO_CONJUGATE).fract() * 360.0;
+    let saturation = 0.6 + (hash & 0x3) as f32 * 0.1;
+    let lightness = 0.55 + ((hash >> 2) & 0x3) as f32 * 0.05;
+    hsl(hue as f32, saturation, lightness)
+}
+
+/// Builds a truecolor `Color` from hue (degrees), saturation and lightness (both `0.0..=1.0`).
+///
+/// Handy for generating evenly spaced, distinct colors by stepping the hue.
+///
+/// ```rust
+/// use blessingrs::{hsl, rgb};
+/// assert_eq!(hsl(0.0, 1.0, 0.5), rgb(255, 0, 0));
+/// assert_eq!(hsl(120.0, 1.0, 0.25), rgb(0, 128, 0));
+/// assert_eq!(hsl(480.0, 1.0, 0.5), hsl(120.0, 1.0, 0.5));
+/// ```
+pub fn hsl(h: f32, s: f32, l: f32) -> Color {
+    let h = h.rem_euclid(360.0) / 60.0;
+    let s = s.clamp(0.0, 1.0);
+    let l = l.clamp(0.0, 1.0);
+
+    let chroma = (1.0 - (2.0 * l - 1.0).abs()) * s;
+    let x = chroma * (1.0 - (h % 2.0 - 1.0).abs());
+    let (r, g, b) = match h as u8 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+
+    let m = l - chroma / 2.0;
+    let channel = |v: f32| ((v + m) * 255.0).round() as u8;
+    rgb(channel(r), channel(g), channel(b))
+}
+
+/// Parses a `#rrggbb` (or bare `rrggbb`) hex color.
+fn parse_hex(hex: &str) -> Option<(u8, u8, u8)> {
+    let digits = hex.strip_prefix('#').unwrap_or(hex);
+    if digits.len() != 6 || !digits.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&digits[i..i + 2], 16).ok();
+    Some((channel(0)?, channel(2)?, channel(4)?))
+}
+
+/// Blends from `start` to `end` by `t` in `0.0..=1.0`.
+fn lerp_rgb(start: (u8, u8, u8), end: (u8, u8, u8), t: f32) -> Color {
+    let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+    Color::Rgb {
+        r: mix(start.0, end.0),
+        g: mix(start.1, end.1),
+        b: mix(start.2, end.2),
+    }
+}
+
+/// Maps a 256-color palette index to a `Color`, using the named colors for 0-15.
+fn ansi_index_color(index: u8) -> Color {
+    const NAMED: [Color; 16] = [
+        Color::Black, Color::DarkRed, Color::DarkGreen, Color::DarkYellow,
+        Color::DarkBlue, Color::DarkMagenta, Color::DarkCyan, Color::Grey,
+        Color::DarkGrey, Color::Red, Color::Green, Color::Yellow,
+        Color::Blue, Color::Magenta, Color::Cyan, Color::White,
+    ];
+    NAMED.get(index as usize).copied().unwrap_or(Color::AnsiValue(index))
+}
+
+/// Approximates the RGB value a terminal would display for `color`.
+fn approx_rgb(color: Color) -> (u8, u8, u8) {
+    let index = match color {
+        Color::Rgb { r, g, b } => return (r, g, b),
+        Color::AnsiValue(n) => n,
+        // Assume the common dark default background.
+        Color::Reset => return (0, 0, 0),
+        Color::Black => 0,
+        Color::DarkRed => 1,
+        Color::DarkGreen => 2,
+        Color::DarkYellow => 3,
+        Color::DarkBlue => 4,
+        Color::DarkMagenta => 5,
+        Color::DarkCyan => 6,
+        Color::Grey => 7,
+        Color::DarkGrey => 8,
+        Color::Red => 9,
+        Color::Green => 10,
+        Color::Yellow => 11,
+        Color::Blue => 12,
+        Color::Magenta => 13,
+        Color::Cyan => 14,
+        Color::White => 15,
+    };
+
+    const BASE: [(u8, u8, u8); 16] = [
+        (0, 0, 0), (205, 0, 0), (0, 205, 0), (205, 205, 0),
+        (0, 0, 238), (205, 0, 205), (0, 205, 205), (229, 229, 229),
+        (127, 127, 127), (255, 0, 0), (0, 255, 0), (255, 255, 0),
+        (92, 92, 255), (255, 0, 255), (0, 255, 255), (255, 255, 255),
+    ];
+
+    match index {
+        0..=15 => BASE[index as usize],
+        16..=231 => {
+            let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+            let i = index - 16;
+            (level(i / 36), level((i / 6) % 6), level(i % 6))
+        }
+        _ => {
+            let v = 8 + (index - 232) * 10;
+            (v, v, v)
+        }
+    }
+}
+
+/// What the attached terminal can interpret, detected from the environment.
+#[derive(Clone, Copy, Debug)]
+struct Capabilities {
+    /// Whether ANSI escape sequences are understood at all (false for `TERM=dumb`).
+    ansi: bool,
+    /// Whether cursor, clear and screen commands work, as escapes or through
+    /// the legacy Windows console API (false only for `TERM=dumb`).
+    commands: bool,
+    /// Whether the less common attributes render rather than showing up as garbage.
+    italic: bool,
+    blink: bool,
+    strikethrough: bool,
+    /// Whether synchronized output (mode 2026) is understood.
+    sync: bool,
+    /// Whether curly, dotted and other `4:n` underline styles are understood.
+    styled_underline: bool,
+    /// Whether 24-bit and 256-color escapes render as intended.
+    truecolor: bool,
+    ansi256: bool,
+}
+
+/// `TERM_PROGRAM` values of emulators known to support truecolor, synchronized output and styled underlines.
+const MODERN_PROGRAMS: [&str; 4] = ["WezTerm", "iTerm.app", "vscode", "ghostty"];
+
+impl Capabilities {
+    fn detect() -> Self {
+        let term = std::env::var("TERM").unwrap_or_default();
+        // The Linux VT and the legacy Windows console (anything not hosted by
+        // Windows Terminal or a TERM-setting emulator) lack these attributes.
+        let linux_console = term == "linux";
+        let legacy_windows = cfg!(windows) && term.is_empty() && std::env::var_os("WT_SESSION").is_none();
+        let limited = linux_console || legacy_windows;
+        let commands = term != "dumb";
+        let ansi = commands && Self::enable_vt();
+
+        Self {
+            ansi,
+            commands,
+            italic: !limited,
+            blink: !legacy_windows,
+            strikethrough: !limited,
+            sync: ansi && Self::detect_sync(&term),
+            styled_underline: ansi && Self::detect_styled_underline(&term),
+            truecolor: ansi && Self::detect_truecolor(&term),
+            ansi256: ansi && (term.contains("256color") || Self::detect_truecolor(&term)),
+        }
+    }
+
+    /// Turns on virtual terminal processing, so a Windows console interprets
+    /// escape sequences instead of printing them.
+    ///
+    /// Consoles older than Windows 10 can't do this; they get plain uncolored
+    /// output rather than escapes shown as literal text, while crossterm
+    /// still moves the cursor and clears through the console API.
+    #[cfg(windows)]
+    fn enable_vt() -> bool {
+        crossterm::ansi_support::supports_ansi()
+    }
+
+    #[cfg(not(windows))]
+    fn enable_vt() -> bool {
+        true
+    }
+
+    /// `COLORTERM=truecolor` (or `24bit`) is the standard signal; `-direct`
+    /// TERMs and a few emulators that don't set it are recognized as well.
+    fn detect_truecolor(term: &str) -> bool {
+        let colorterm = std::env::var("COLORTERM").unwrap_or_default();
+        let program = std::env::var("TERM_PROGRAM").unwrap_or_default();
+        matches!(colorterm.as_str(), "truecolor" | "24bit")
+            || term.ends_with("-direct")
+            || MODERN_PROGRAMS.contains(&program.as_str())
+            || std::env::var_os("WT_SESSION").is_some()
+    }
+
+    /// Guesses synchronized output support from the environment, since
+    /// querying it would mean reading a reply from the terminal.
+    fn detect_sync(term: &str) -> bool {
+        const TERMS: [&str; 5] = ["xterm-kitty", "xterm-ghostty", "foot", "alacritty", "contour"];
+
+        let program = std::env::var("TERM_PROGRAM").unwrap_or_default();
+        TERMS.iter().any(|t| term.starts_with(t))
+            || MODERN_PROGRAMS.contains(&program.as_str())
+            || std::env::var_os("WT_SESSION").is_some()
+    }
+
+    /// Guesses styled underline support from the environment, like `detect_sync`.
+    fn detect_styled_underline(term: &str) -> bool {
+        const TERMS: [&str; 5] = ["xterm-kitty", "xterm-ghostty", "foot", "alacritty", "wezterm"];
+
+        let program = std::env::var("TERM_PROGRAM").unwrap_or_default();
+        TERMS.iter().any(|t| term.starts_with(t))
+            || MODERN_PROGRAMS.contains(&program.as_str())
+            || std::env::var_os("VTE_VERSION").is_some()
+    }
+
+    fn full() -> Self {
+        Self {
+            ansi: true,
+            commands: true,
+            italic: true,
+            blink: true,
+            strikethrough: true,
+            sync: true,
+            styled_underline: true,
+            truecolor: true,
+            ansi256: true,
+        }
+    }
+}
+
+/// How long to wait for the terminal to answer a query.
+const QUERY_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Reads from the controlling tty until `complete` accepts what has arrived, or `timeout` passes.
+///
+/// Bytes are read one at a time so nothing typed after the reply is consumed.
+#[cfg(unix)]
+fn read_reply(timeout: Duration, complete: impl Fn(&[u8]) -> bool) -> io::Result<Vec<u8>> {
+    use std::os::fd::AsRawFd;
+
+    let tty = std::fs::File::open("/dev/tty")?;
+    let deadline = Instant::now() + timeout;
+    let mut reply = Vec::new();
+    let mut buf = [0u8; 1];
+    while !complete(&reply) {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "the terminal didn't reply in time"));
+        }
+
+        let mut fd = libc::pollfd { fd: tty.as_raw_fd(), events: libc::POLLIN, revents: 0 };
+        // SAFETY: `fd` is a single valid pollfd for the duration of the call.
+        let ready = unsafe { libc::poll(&mut fd, 1, left.as_millis().max(1) as libc::c_int) };
+        if ready < 0 {
+            let err = io::Error::last_os_error();
+            if err.kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(err);
+        }
+        if ready == 0 {
+            continue;
+        }
+
+        // SAFETY: `buf` is valid for writes of `buf.len()` bytes.
+        let read = unsafe { libc::read(tty.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len()) };
+        match read {
+            n if n > 0 => reply.extend_from_slice(&buf[..n as usize]),
+            0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+            _ => {
+                let err = io::Error::last_os_error();
+                if err.kind() != io::ErrorKind::Interrupted {
+                    return Err(err);
+                }
+            }
+        }
+    }
+    Ok(reply)
+}
+
+#[cfg(not(unix))]
+fn read_reply(_timeout: Duration, _complete: impl Fn(&[u8]) -> bool) -> io::Result<Vec<u8>> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "terminal queries need a Unix tty"))
+}
+
+/// Returns this machine's host name, or an empty string if it can't be read.
+#[cfg(unix)]
+fn hostname() -> String {
+    let mut buf = [0u8; 256];
+    // SAFETY: `buf` is valid for writes of `buf.len()` bytes.
+    if unsafe { libc::gethostname(buf.as_mut_ptr().cast(), buf.len()) } != 0 {
+        return String::new();
+    }
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    String::from_utf8_lossy(&buf[..len]).into_owned()
+}
+
+#[cfg(not(unix))]
+fn hostname() -> String {
+    std::env::var("COMPUTERNAME").unwrap_or_default()
+}
+
+/// Builds a `file://host/path` URL for `path`, percent-encoding anything unsafe.
+fn file_url(host: &str, path: &std::path::Path) -> String {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut url = format!("file://{}", host);
+    if !path.starts_with('/') {
+        url.push('/');
+    }
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => url.push(byte as char),
+            _ => url.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    url
+}
+
+/// Whether to use colors, overriding what's detected from the environment.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Detect support from `TERM`, `COLORTERM` and friends.
+    #[default]
+    Auto,
+    /// Assume every color renders, truecolor included.
+    Always,
+    /// Leave colors out entirely.
+    Never,
+}
+
+/// The non-ASCII glyphs the drawing helpers use, with ASCII stand-ins.
+struct Glyphs {
+    horizontal: &'static str,
+    vertical: &'static str,
+    top_left: &'static str,
+    top_right: &'static str,
+    bottom_left: &'static str,
+    bottom_right: &'static str,
+    ellipsis: &'static str,
+    scroll_thumb: &'static str,
+    overflow: &'static str,
+    /// Sparkline bars from lowest to highest.
+    sparks: [&'static str; 8],
+}
+
+impl Glyphs {
+    const UNICODE: Glyphs = Glyphs {
+        horizontal: "─",
+        vertical: "│",
+        top_left: "┌",
+        top_right: "┐",
+        bottom_left: "└",
+        bottom_right: "┘",
+        ellipsis: "…",
+        scroll_thumb: "█",
+        overflow: "▼",
+        sparks: ["▁", "▂", "▃", "▄", "▅", "▆", "▇", "█"],
+    };
+
+    const ASCII: Glyphs = Glyphs {
+        horizontal: "-",
+        vertical: "|",
+        top_left: "+",
+        top_right: "+",
+        bottom_left: "+",
+        bottom_right: "+",
+        ellipsis: "...",
+        scroll_thumb: "#",
+        overflow: "v",
+        sparks: ["_", ".", ",", "-", "~", "=", "*", "#"],
+    };
+}
+
+/// A closure registered with [`Terminal::on_cleanup`].
+type Cleanup = Box<dyn FnOnce(&mut Terminal) + Send>;
+
+/// A closure registered with [`Terminal::set_key_handler`].
+type KeyHandler = Box<dyn FnMut(&mut Terminal, KeyEvent) + Send>;
+
+/// The main entry point for managing the terminal state.
+pub struct Terminal {
+    writer: BufWriter<Output>,
+    headless: bool,
+    caps: Capabilities,
+    cursor_visible: bool,
+    alternate_screen: bool,
+    clear_on_drop: bool,
+    force_all_attributes: bool,
+    ambiguous_width: AmbiguousWidth,
+    capture: Option<Capture>,
+    last_frame: Vec<DrawOp>,
+    raw_mode: bool,
+    mouse_capture: bool,
+    focus_events: bool,
+    focused: bool,
+    pause_on_blur: bool,
+    mouse_position: Option<(u16, u16)>,
+    cursor_pos: Option<(u16, u16)>,
+    ascii_only: bool,
+    ellipsis: Option<String>,
+    overflow_indicator: Option<String>,
+    color_choice: ColorChoice,
+    /// Palette entries changed by `set_palette_color`, with their original colors if known.
+    palette_saved: Vec<(u8, Option<Color>)>,
+    key_repeat: Option<KeyRepeat>,
+    /// Events read ahead of a terminal query, handed out before any new input.
+    pending_events: VecDeque<Event>,
+    toast: Option<Toast>,
+    cleanups: Vec<Cleanup>,
+    key_handler: Option<KeyHandler>,
+    quit_requested: bool,
+    size_source: fn() -> io::Result<(u16, u16)>,
+    size_override: Option<Size>,
+    last_size: Cell<Option<Size>>,
+    #[cfg(all(unix, feature = "sigwinch"))]
+    resize_signal: Option<(signal_hook::SigId, Arc<AtomicBool>)>,
+}
+
+impl Terminal {
+    /// Initializes the terminal, enables raw mode, and switches to the alternate screen.
+    ///
+    /// The alternate screen is entered with `\x1b[?1049h`, and the cursor is
+    /// additionally saved (`\x1b7`) just before and restored (`\x1b8`) just
+    /// after leaving it on drop. So even on terminals whose 1049 mode doesn't
+    /// save the cursor, the shell prompt comes back exactly where it was.
+    ///
+    /// On a `TERM=dumb` terminal no escape sequences are ever written: the
+    /// alternate screen is skipped and styling and cursor movement become no-ops.
+    ///
+    /// Use [`Terminal::builder`] for other setups.
+    pub fn new() -> Self {
+        TerminalBuilder::new().build()
+    }
+
+    /// Starts configuring a terminal, e.g. one that stays on the main screen.
+    pub fn builder() -> TerminalBuilder {
+        TerminalBuilder::new()
+    }
+
+    fn with_output(output: Output, caps: Capabilities) -> Self {
+        Self {
+            headless: matches!(output, Output::Memory(_)),
+            writer: BufWriter::new(output),
+            caps,
+            cursor_visible: true,
+            alternate_screen: false,
+            clear_on_drop: false,
+            force_all_attributes: false,
+            ambiguous_width: AmbiguousWidth::Narrow,
+            capture: None,
+            last_frame: Vec::new(),
+            raw_mode: false,
+            mouse_capture: false,
+            focus_events: false,
+            focused: true,
+            pause_on_blur: false,
+            mouse_position: None,
+            cursor_pos: None,
+            ascii_only: false,
+            ellipsis: None,
+            overflow_indicator: None,
+            color_choice: ColorChoice::Auto,
+            palette_saved: Vec::new(),
+            pending_events: VecDeque::new(),
+            toast: None,
+            key_repeat: None,
+            cleanups: Vec::new(),
+            key_handler: None,
+            quit_requested: false,
+            size_source: terminal::size,
+            size_override: None,
+            last_size: Cell::new(None),
+            #[cfg(all(unix, feature = "sigwinch"))]
+            resize_signal: None,
+        }
+    }
+
+    /// Creates a terminal with no side effects, writing into an in-memory buffer.
+    ///
+    /// Raw mode and the alternate screen are left alone, both here and on drop,
+    /// so this works without a TTY (e.g. in tests or CI). Its size is a fixed 80x24.
+    ///
+    /// ```rust
+    /// use blessingrs::Terminal;
+    /// let mut term = Terminal::headless();
+    /// term.move_to(0, 0).print("hi").flush();
+    /// assert_eq!(term.take_output(), b"\x1b[1;1Hhi");
+    /// ```
+    pub fn headless() -> Self {
+        let mut term = Self::with_output(Output::Memory(Vec::new()), Capabilities::full());
+        term.size_source = || Ok((80, 24));
+        term
+    }
+
+    /// Returns false when the terminal can't interpret escape sequences (`TERM=dumb`).
+    pub fn supports_ansi(&self) -> bool {
+        self.caps.ansi
+    }
+
+    /// Returns whether `#rrggbb` colors will render as intended.
+    ///
+    /// With [`ColorChoice::Auto`] this is true when `COLORTERM` is `truecolor`
+    /// or `24bit`, `TERM` ends in `-direct`, or the terminal is known to
+    /// support truecolor without saying so (Windows Terminal, iTerm2, WezTerm,
+    /// VS Code, Ghostty). `Always` makes it true and `Never` false.
+    ///
+    /// ```rust
+    /// use blessingrs::{ColorChoice, Terminal};
+    /// let mut term = Terminal::headless();
+    /// term.set_color_choice(ColorChoice::Never);
+    /// let spec = if term.supports_truecolor() { "#ff8800" } else { "yellow" };
+    /// assert_eq!(spec, "yellow");
+    /// assert_eq!(term.style("red", "hi"), "hi");
+    /// ```
+    pub fn supports_truecolor(&self) -> bool {
+        match self.color_choice {
+            ColorChoice::Auto => self.caps.truecolor,
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+
+    /// Returns whether 256-color indexes (e.g. `208`) will render as intended.
+    ///
+    /// With [`ColorChoice::Auto`] this is true when `TERM` contains
+    /// `256color` or truecolor is supported.
+    pub fn supports_256(&self) -> bool {
+        match self.color_choice {
+            ColorChoice::Auto => self.caps.ansi256,
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+
+    /// Overrides color detection, e.g. from a `--color=always|never|auto` flag.
+    ///
+    /// `Never` also leaves colors out of everything drawn, keeping attributes
+    /// like bold.
+    pub fn set_color_choice(&mut self, choice: ColorChoice) -> &mut Self {
+        self.color_choice = choice;
+        self
+    }
+
+    /// Returns whether colors should be emitted at all.
+    fn colors_enabled(&self) -> bool {
+        self.caps.ansi && self.color_choice != ColorChoice::Never
+    }
+
+    /// Returns `false` if the terminal isn't known to support synchronized output.
+    pub fn supports_sync(&self) -> bool {
+        self.caps.sync
+    }
+
+    /// Writes `request` and reads the terminal's reply until `complete` says it has all arrived.
+    ///
+    /// Raw mode is turned on for the duration if it isn't already, so the reply
+    /// isn't echoed or held back until Enter. Input that has already arrived
+    /// is set aside in `pending_events` first, and the reply is read from the
+    /// tty rather than stdin, so neither keys nor piped input are lost.
+    fn query(&mut self, request: &str, complete: impl Fn(&[u8]) -> bool) -> io::Result<Vec<u8>> {
+        if self.headless || !self.caps.ansi {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, "no terminal to query"));
+        }
+        if matches!(self.writer.get_ref(), Output::Stderr(_)) {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, "terminal queries are sent on stdout"));
+        }
+
+        let was_raw = terminal::is_raw_mode_enabled()?;
+        if !was_raw {
+            terminal::enable_raw_mode()?;
+        }
+        while event::poll(Duration::ZERO)? {
+            let event = event::read()?;
+            self.pending_events.push_back(event);
+        }
+        let reply = write!(self.writer, "{}", request)
+            .and_then(|_| self.writer.flush())
+            .and_then(|_| read_reply(QUERY_TIMEOUT, complete));
+        if !was_raw {
+            terminal::disable_raw_mode()?;
+        }
+        reply
+    }
+
+    /// Tells the terminal the app's working directory (OSC 7), e.g. for where new tabs open.
+    ///
+    /// There's no standard way to ask the terminal for it in return; terminals
+    /// that track OSC 7 just use what the shell or app last reported. Use
+    /// `std::env::current_dir` to get the process' own directory.
+    ///
+    /// ```rust
+    /// let mut term = blessingrs::Terminal::headless();
+    /// term.set_working_directory("/srv/my files");
+    /// let out = String::from_utf8(term.take_output()).unwrap();
+    /// assert!(out.starts_with("\x1b]7;file://") && out.ends_with("/srv/my%20files\x1b\\"));
+    /// ```
+    pub fn set_working_directory(&mut self, path: impl AsRef<std::path::Path>) -> &mut Self {
+        let url = file_url(&hostname(), path.as_ref());
+        let _ = self.write_escape(&format!("\x1b]7;{}\x1b\\", url));
+        self
+    }
+
+    /// Remaps palette entry `index` to `color` (OSC 4) until the terminal is dropped.
+    ///
+    /// Lets an app use its own colors through `AnsiValue(index)` without
+    /// needing truecolor. The first time an entry is changed its current value
+    /// is queried, so it can be put back on drop; if the terminal doesn't
+    /// answer, the entry is reset to the terminal's default instead.
+    ///
+    /// ```rust
+    /// use blessingrs::{rgb, Terminal};
+    /// let mut term = Terminal::headless();
+    /// term.set_palette_color(16, rgb(255, 136, 0));
+    /// assert_eq!(term.take_output(), b"\x1b]4;16;rgb:ff/88/00\x1b\\");
+    /// ```
+    pub fn set_palette_color(&mut self, index: u8, color: Color) -> &mut Self {
+        if !self.caps.ansi {
+            return self;
+        }
+        if !self.palette_saved.iter().any(|&(saved, _)| saved == index) {
+            let original = self.palette_color(index).ok();
+            self.palette_saved.push((index, original));
+        }
+        let (r, g, b) = approx_rgb(color);
+        let _ = write!(self.writer, "\x1b]4;{};rgb:{:02x}/{:02x}/{:02x}\x1b\\", index, r, g, b);
+        self
+    }
+
+    /// Asks the terminal for the current color of palette entry `index` (OSC 4 query).
+    ///
+    /// Fails if the terminal doesn't answer in time, for a headless terminal,
+    /// and when drawing on stderr. Keys pressed meanwhile stay queued for
+    /// `drain_events`.
+    pub fn palette_color(&mut self, index: u8) -> io::Result<Color> {
+        let reply = self.query(&format!("\x1b]4;{};?\x1b\\", index), osc_complete)?;
+        parse_osc_color(&reply).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed palette reply"))
+    }
+
+    /// Asks the terminal for its name and version, e.g. `"WezTerm 20240203"`.
+    ///
+    /// Sends an XTVERSION query (`\x1b[>q`) followed by a primary device
+    /// attributes request, which every terminal answers, so terminals without
+    /// XTVERSION don't leave us waiting. Returns `None` if the terminal doesn't
+    /// report a name, and errors for a headless terminal or when drawing on
+    /// stderr. Like `palette_color`, pending keys and piped stdin are left alone.
+    ///
+    /// ```rust
+    /// let mut term = blessingrs::Terminal::headless();
+    /// assert!(term.query_terminal_name().is_err());
+    /// ```
+    pub fn query_terminal_name(&mut self) -> io::Result<Option<String>> {
+        match self.query("\x1b[>q\x1b[c", device_attributes_complete) {
+            Ok(reply) => Ok(parse_xtversion(&reply)),
+            Err(err) if err.kind() == io::ErrorKind::TimedOut => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Puts back every palette entry changed by `set_palette_color`.
+    fn restore_palette(&mut self) {
+        for (index, original) in std::mem::take(&mut self.palette_saved) {
+            let _ = match original {
+                Some(color) => {
+                    let (r, g, b) = approx_rgb(color);
+                    write!(self.writer, "\x1b]4;{};rgb:{:02x}/{:02x}/{:02x}\x1b\\", index, r, g, b)
+                }
+                None => write!(self.writer, "\x1b]104;{}\x1b\\", index),
+            };
+        }
+    }
+
+    /// Asks the terminal where the cursor is, returning its 0-based `(x, y)`.
+    ///
+    /// Sends a cursor position request (`\x1b[6n`) and waits up to two seconds
+    /// for the `\x1b[{row};{col}R` reply. Asking right after printing tells
+    /// how wide the text actually rendered.
+    ///
+    /// The reply is read through crossterm's event reader, so keys pressed
+    /// while waiting aren't lost: they're still there for `drain_events` and
+    /// the other input handlers. Fails if the terminal doesn't answer in time,
+    /// for a headless terminal, and when drawing on stderr, since the request
+    /// always goes to stdout.
+    ///
+    /// ```rust
+    /// assert!(blessingrs::Terminal::headless().request_cursor_report().is_err());
+    /// ```
+    pub fn request_cursor_report(&mut self) -> io::Result<(u16, u16)> {
+        if self.headless || !self.caps.ansi {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, "no terminal to query"));
+        }
+        if matches!(self.writer.get_ref(), Output::Stderr(_)) {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, "cursor reports are requested on stdout"));
+        }
+        self.writer.flush()?;
+        let position = cursor::position()?;
+        self.cursor_pos = Some(position);
+        Ok(position)
+    }
+
+    /// Queues a crossterm command, skipped on terminals that can't carry it out.
+    fn queue_command(&mut self, command: impl crossterm::Command) -> io::Result<()> {
+        if self.caps.commands {
+            queue!(self.writer, command)?;
+        }
+        Ok(())
+    }
+
+    /// Writes a raw escape sequence, skipped on terminals that can't interpret it.
+    fn write_escape(&mut self, escape: &str) -> io::Result<()> {
+        if self.caps.ansi {
+            self.writer.write_all(escape.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Flushes and returns everything written to a headless terminal so far.
+    ///
+    /// Always empty for a terminal backed by stdout.
+    pub fn take_output(&mut self) -> Vec<u8> {
+        self.flush();
+        match self.writer.get_mut() {
+            Output::Memory(out) => std::mem::take(out),
+            Output::Stdout(_) | Output::Stderr(_) => Vec::new(),
+        }
+    }
+    
+    /// Returns the size of the terminal (returns a struct with .x and .y u16s).
+    ///
+    /// Some emulators briefly report `0x0` (e.g. during startup). An error or
+    /// a zero width or height falls back to the last good size seen, or to
+    /// 80x24 if there hasn't been one, so layout code never sees an empty screen.
+    ///
+    /// ```rust
+    /// use blessingrs::{Size, Terminal};
+    /// let mut term = Terminal::headless();
+    /// term.set_size_source(|| Ok((0, 0)));
+    /// assert_eq!(term.size(), Size { x: 80, y: 24 });
+    ///
+    /// term.set_size_source(|| Ok((100, 30)));
+    /// assert_eq!(term.size(), Size { x: 100, y: 30 });
+    /// term.set_size_source(|| Ok((100, 0)));
+    /// assert_eq!(term.size(), Size { x: 100, y: 30 });
+    /// ```
+    pub fn size(&self) -> Size {
+        if let Some(size) = self.size_override {
+            return size;
+        }
+        match (self.size_source)() {
+            Ok((x, y)) if x > 0 && y > 0 => {
+                let size = Size { x, y };
+                self.last_size.set(Some(size));
+                size
+            }
+            _ => self.last_size.get().unwrap_or(Size { x: 80, y: 24 }),
+        }
+    }
+
+    /// Returns the size last seen by `size`, only asking the terminal if there is none yet.
+    ///
+    /// For bookkeeping on every op, where a syscall each time would add up.
+    fn cached_size(&self) -> Size {
+        self.size_override.or_else(|| self.last_size.get()).unwrap_or_else(|| self.size())
+    }
+
+    /// Sets how ambiguous-width characters are counted when laying out text (default `Narrow`).
+    ///
+    /// Choose `Wide` for terminals configured for East Asian locales, so
+    /// titles, menus and fields line up. Box-drawing and block glyphs are
+    /// ambiguous too, so in `Wide` mode the drawing helpers use their ASCII
+    /// glyphs (as with `set_output_ascii_only`) to keep boxes the size asked for:
+    ///
+    /// ```rust
+    /// use blessingrs::{display_width_with, AmbiguousWidth, DrawOp, Terminal};
+    /// let mut term = Terminal::headless();
+    /// term.set_ambiguous_width(AmbiguousWidth::Wide).start_capture(false);
+    /// term.draw_box(0, 0, 6, 3, "");
+    /// let ops = term.take_ops();
+    /// assert_eq!(ops[1], DrawOp::Print(Some(Default::default()), "+----+".into()));
+    /// assert!(ops.iter().all(|op| match op {
+    ///     DrawOp::Print(_, text) => display_width_with(text, AmbiguousWidth::Wide) <= 6,
+    ///     _ => true,
+    /// }));
+    /// ```
+    pub fn set_ambiguous_width(&mut self, width: AmbiguousWidth) -> &mut Self {
+        self.ambiguous_width = width;
+        self
+    }
+
+    /// Returns the columns `text` occupies, honoring `set_ambiguous_width`.
+    pub fn display_width(&self, text: &str) -> usize {
+        display_width_with(text, self.ambiguous_width)
+    }
+
+    /// Replaces the box-drawing, block and ellipsis glyphs the drawing helpers use with ASCII.
+    ///
+    /// For terminals that don't use UTF-8, where those glyphs come out as
+    /// garbage. Text passed in by the caller is left alone.
+    ///
+    /// ```rust
+    /// use blessingrs::{DrawOp, Terminal};
+    /// let mut term = Terminal::headless();
+    /// term.set_output_ascii_only(true).start_capture(false);
+    /// term.draw_box_titled(0, 0, 14, 2, "Long title", blessingrs::Align::Left, "");
+    /// assert_eq!(term.take_ops()[1], DrawOp::Print(Some(Default::default()), "+- Long ... -+".into()));
+    /// ```
+    pub fn set_output_ascii_only(&mut self, ascii_only: bool) -> &mut Self {
+        self.ascii_only = ascii_only;
+        self
+    }
+
+    fn glyphs(&self) -> &'static Glyphs {
+        if self.ascii_glyphs() { &Glyphs::ASCII } else { &Glyphs::UNICODE }
+    }
+
+    /// Whether to draw with ASCII glyphs, which is also needed when ambiguous-width glyphs take two columns.
+    fn ascii_glyphs(&self) -> bool {
+        self.ascii_only || self.ambiguous_width == AmbiguousWidth::Wide
+    }
+
+    /// Sets what the drawing helpers put where they cut text short, e.g. `"..."`.
+    ///
+    /// Defaults to `…`, or `...` with `set_output_ascii_only`. Room is made
+    /// for the ellipsis' full display width when deciding where to cut:
+    ///
+    /// ```rust
+    /// use blessingrs::{DrawOp, Terminal};
+    /// let mut term = Terminal::headless();
+    /// term.set_ellipsis(" [more]").start_capture(false);
+    /// term.panel(0, 0, 15, 3, "", "a long line that overflows", "", "");
+    /// assert!(term.take_ops().contains(&DrawOp::Print(Some(Default::default()), "a long [more]".into())));
+    /// ```
+    pub fn set_ellipsis(&mut self, ellipsis: &str) -> &mut Self {
+        self.ellipsis = Some(ellipsis.to_string());
+        self
+    }
+
+    /// Sets the marker a scroll view's scrollbar shows while there's more content below.
+    ///
+    /// Defaults to `▼`, or `v` with `set_output_ascii_only`.
+    pub fn set_overflow_indicator(&mut self, indicator: &str) -> &mut Self {
+        self.overflow_indicator = Some(indicator.to_string());
+        self
+    }
+
+    fn ellipsis(&self) -> &str {
+        self.ellipsis.as_deref().unwrap_or(self.glyphs().ellipsis)
+    }
+
+    fn overflow_indicator(&self) -> &str {
+        self.overflow_indicator.as_deref().unwrap_or(self.glyphs().overflow)
+    }
+
+    /// Shortens `text` to `width` columns, honoring `set_ambiguous_width` and `set_ellipsis`.
+    fn truncate(&self, text: &str, width: usize) -> String {
+        truncate_with(text, width, self.ellipsis(), self.ambiguous_width)
+    }
+
+    /// Replaces how `size` queries the terminal, e.g. to simulate odd sizes in tests.
+    pub fn set_size_source(&mut self, source: fn() -> io::Result<(u16, u16)>) {
+        self.size_source = source;
+    }
+
+    /// Makes `size` return `size` instead of asking the terminal, until set back to `None`.
+    ///
+    /// Useful for testing how a layout comes out at specific dimensions.
+    ///
+    /// ```rust
+    /// use blessingrs::{Size, Terminal};
+    /// let mut term = Terminal::headless();
+    /// for (x, y) in [(40, 10), (200, 50)] {
+    ///     term.set_size_override(Some(Size { x, y }));
+    ///     assert_eq!(term.size(), Size { x, y });
+    /// }
+    /// term.set_size_override(None);
+    /// assert_eq!(term.size(), Size { x: 80, y: 24 });
+    /// ```
+    pub fn set_size_override(&mut self, size: Option<Size>) -> &mut Self {
+        self.size_override = size;
+        self
+    }
+
+    /// Starts watching for `SIGWINCH` so resizes are caught even when events aren't drained.
+    ///
+    /// The signal handler only sets a flag; check it with `poll_resize`.
+    #[cfg(all(unix, feature = "sigwinch"))]
+    pub fn watch_resize(&mut self) -> io::Result<()> {
+        if self.resize_signal.is_none() {
+            let flag = Arc::new(AtomicBool::new(false));
+            let id = signal_hook::flag::register(signal_hook::consts::SIGWINCH, Arc::clone(&flag))?;
+            self.resize_signal = Some((id, flag));
+        }
+        Ok(())
+    }
+
+    /// Returns the new size if the window was resized since the last call.
+    ///
+    /// Only reports anything after `watch_resize` has been called.
+    #[cfg(all(unix, feature = "sigwinch"))]
+    pub fn poll_resize(&mut self) -> Option<Size> {
+        let (_, flag) = self.resize_signal.as_ref()?;
+        flag.swap(false, Ordering::Relaxed).then(|| self.size())
+    }
+
+    /// Flushes the buffer and pauses execution for `ms` milliseconds.
+    /// Requires an active Tokio runtime.
+    #[cfg(feature = "async")]
+    pub async fn sleep(&mut self, ms: u64) {
+        self.flush();
+        tokio::time::sleep(Duration::from_millis(ms)).await;
+    }
+
+    /// Shows `text` in the bottom-right corner for `ms` milliseconds, replacing any earlier toast.
+    ///
+    /// This returns straight away. Once the time is up, `run` and
+    /// `drain_events` remove the toast, or call `expire_toast` from your own
+    /// loop. Only the covered cells are restored from the last
+    /// [`Terminal::frame`], and frames drawn meanwhile keep the toast on top.
+    ///
+    /// ```rust
+    /// use blessingrs::{Size, Terminal};
+    /// let mut term = Terminal::headless();
+    /// term.set_size_override(Some(Size { x: 20, y: 5 }));
+    /// term.frame(|t| {
+    ///     t.move_to(0, 4).print("status: all good");
+    /// });
+    /// term.take_output();
+    /// term.toast("Saved", "", 0);
+    /// assert_eq!(term.take_output(), b"\x1b[5;14H Saved ");
+    /// term.expire_toast();
+    /// assert_eq!(term.take_output(), b"\x1b[5;14H       \x1b[5;14Hood");
+    /// ```
+    pub fn toast(&mut self, text: &str, style_spec: &str, ms: u64) -> &mut Self {
+        self.clear_toast();
+        let size = self.size();
+        let label = self.truncate(&format!(" {} ", text), size.x as usize);
+        let width = display_width_with(&label, self.ambiguous_width) as u16;
+        self.toast = Some(Toast {
+            x: size.x.saturating_sub(width),
+            y: size.y.saturating_sub(1),
+            width,
+            label,
+            style: compile_spec(style_spec),
+            expires: Instant::now() + Duration::from_millis(ms),
+        });
+        self.show_toast().flush();
+        self
+    }
+
+    /// Removes the toast from [`Terminal::toast`] if its time is up.
+    pub fn expire_toast(&mut self) -> &mut Self {
+        if self.toast.as_ref().is_some_and(|toast| Instant::now() >= toast.expires) {
+            self.clear_toast().flush();
+        }
+        self
+    }
+
+    fn show_toast(&mut self) -> &mut Self {
+        if let Some(toast) = &self.toast {
+            let (x, y, style, label) = (toast.x, toast.y, toast.style, toast.label.clone());
+            self.print_clipped(x, y, Some(&style), &label);
+        }
+        self
+    }
+
+    /// Erases the current toast, putting back what the last frame drew under it.
+    fn clear_toast(&mut self) -> &mut Self {
+        let Some(Toast { x: left, y: row, width, .. }) = self.toast.take() else {
+            return self;
+        };
+        let right = left.saturating_add(width);
+        let ambiguous = self.ambiguous_width;
+        self.print_clipped(left, row, None, &" ".repeat(width as usize));
+
+        let ops = self.last_frame.clone();
+        let (mut x, mut y) = (0u16, 0u16);
+        for op in ops {
+            match op {
+                DrawOp::MoveTo(to_x, to_y) => (x, y) = (to_x, to_y),
+                DrawOp::Print(style, text) => {
+                    let (_, used) = clip_ansi(&text, usize::MAX, ambiguous);
+                    let end = x.saturating_add(used as u16);
+                    if y == row && x < right && end > left {
+                        let (from, to) = (x.max(left), end.min(right));
+                        let covered = slice_columns_with(&text, (from - x) as usize, (to - from) as usize, ambiguous);
+                        self.print_clipped(from, row, style.as_ref(), &covered);
+                    }
+                    x = end;
+                }
+                _ => {}
+            }
+        }
+        self
+    }
+
+    /// Flushes the buffer and blocks the current thread for `ms` milliseconds.
+    pub fn sleep_blocking(&mut self, ms: u64) {
+        self.flush();
+        std::thread::sleep(Duration::from_millis(ms));
+    }
+
+    /// Polls for input and executes a closure if a key event occurs.
+    /// 
+    /// This is non-blocking with a tiny timeout (1ms), making it 
+    /// perfect for high-frequency game loops or animations.
+    ///
+    /// Returns `true` if the handler was called, so idle frames can skip redrawing.
+    pub fn add_input_handler<F>(&mut self, mut handler: F) -> io::Result<bool>
+    where
+        F: FnMut(KeyEvent),
+    {
+        // Poll briefly to see if an event is available
+        if let Some(Event::Key(key)) = self.next_event(Duration::from_millis(1))?
+            // Filter for Press to avoid double-triggers on Windows
+            && key.kind == KeyEventKind::Press
+        {
+            handler(key);
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    /// Registers `cleanup` to run when the terminal is dropped, even during a panic.
+    ///
+    /// Cleanups run last-registered first, before the built-in teardown, so
+    /// they can revert modes they turned on with their own escape sequences.
+    /// A cleanup that panics doesn't stop the others from running.
+    ///
+    /// ```rust
+    /// use std::sync::{Arc, Mutex};
+    /// let order = Arc::new(Mutex::new(Vec::new()));
+    /// let mut term = blessingrs::Terminal::headless();
+    /// for name in ["focus", "paste"] {
+    ///     let order = order.clone();
+    ///     term.on_cleanup(move |_| order.lock().unwrap().push(name));
+    /// }
+    /// term.on_cleanup(|_| panic!("broken cleanup"));
+    /// drop(term);
+    /// assert_eq!(*order.lock().unwrap(), ["paste", "focus"]);
+    /// ```
+    pub fn on_cleanup<F>(&mut self, cleanup: F) -> &mut Self
+    where
+        F: FnOnce(&mut Terminal) + Send + 'static,
+    {
+        self.cleanups.push(Box::new(cleanup));
+        self
+    }
+
+    /// Starts reporting mouse clicks, scrolls and movement as events.
+    ///
+    /// Capture is turned off again when the terminal is dropped. Does nothing
+    /// on a `TERM=dumb` terminal.
+    pub fn enable_mouse_capture(&mut self) -> &mut Self {
+        if self.caps.ansi && self.queue_command(event::EnableMouseCapture).is_ok() {
+            self.mouse_capture = true;
+        }
+        self
+    }
+
+    pub fn disable_mouse_capture(&mut self) -> &mut Self {
+        if self.mouse_capture && self.queue_command(event::DisableMouseCapture).is_ok() {
+            self.mouse_capture = false;
+        }
+        self
+    }
+
+    /// Starts reporting focus changes as `FocusGained`/`FocusLost` events.
+    ///
+    /// Reporting is turned off again when the terminal is dropped. Does nothing
+    /// on a `TERM=dumb` terminal.
+    pub fn enable_focus_events(&mut self) -> &mut Self {
+        if self.caps.ansi && self.queue_command(event::EnableFocusChange).is_ok() {
+            self.focus_events = true;
+        }
+        self
+    }
+
+    pub fn disable_focus_events(&mut self) -> &mut Self {
+        if self.focus_events && self.queue_command(event::DisableFocusChange).is_ok() {
+            self.focus_events = false;
+        }
+        self
+    }
+
+    /// Returns whether the terminal window has focus, as of the last focus event seen.
+    ///
+    /// Always `true` until `enable_focus_events` is on and a `FocusLost` arrives.
+    pub fn is_focused(&self) -> bool {
+        self.focused
+    }
+
+    /// Makes [`Terminal::run`] stop drawing frames while the terminal is unfocused.
+    ///
+    /// Input and focus events are still handled, so drawing resumes as soon
+    /// as focus comes back. Needs `enable_focus_events`; without it the
+    /// terminal always counts as focused.
+    ///
+    /// ```rust,no_run
+    /// let mut term = blessingrs::Terminal::new();
+    /// term.enable_focus_events().set_pause_on_blur(true);
+    /// let mut spinner = 0;
+    /// term.run(30, |term| {
+    ///     spinner += 1;
+    ///     term.move_to(0, 0).print(["|", "/", "-", "\\"][spinner % 4]);
+    /// }).unwrap();
+    /// ```
+    pub fn set_pause_on_blur(&mut self, on: bool) -> &mut Self {
+        self.pause_on_blur = on;
+        self
+    }
+
+    /// Like `add_input_handler`, but passes every event through, including mouse and resize events.
+    ///
+    /// Key events are still filtered to presses. Mouse events also update
+    /// [`Terminal::mouse_position`], so hover effects can be drawn each frame:
+    ///
+    /// ```rust,no_run
+    /// use blessingrs::{hit_test, Terminal};
+    /// let mut term = Terminal::new();
+    /// term.enable_mouse_capture();
+    /// let items = ["Start", "Quit"];
+    /// loop {
+    ///     term.add_event_handler(|_| {}).unwrap();
+    ///     for (i, item) in items.iter().enumerate() {
+    ///         let row = (2, 2 + i as u16, 10, 1);
+    ///         let hovered = term.mouse_position().is_some_and(|(mx, my)| hit_test(row, mx, my));
+    ///         let spec = if hovered { "black_on_white" } else { "white" };
+    ///         term.style_print_at(2, 2 + i as u16, spec, item);
+    ///     }
+    ///     term.flush();
+    /// }
+    /// ```
+    pub fn add_event_handler<F>(&mut self, mut handler: F) -> io::Result<bool>
+    where
+        F: FnMut(Event),
+    {
+        let Some(event) = self.next_event(Duration::from_millis(1))? else {
+            return Ok(false);
+        };
+        match event {
+            Event::Key(key) if key.kind != KeyEventKind::Press => Ok(false),
+            event => {
+                match &event {
+                    Event::Mouse(mouse) => self.mouse_position = Some((mouse.column, mouse.row)),
+                    Event::FocusGained => self.focused = true,
+                    Event::FocusLost => self.focused = false,
+                    _ => {}
+                }
+                handler(event);
+                Ok(true)
+            }
+        }
+    }
+
+    /// Handles every pending event without blocking, returning `true` if the handler was called.
+    ///
+    /// Like `add_event_handler`, key events are filtered to presses and mouse
+    /// events update `mouse_position`. With `set_key_repeat`, a held key also
+    /// produces repeats here (as [`KeyEventKind::Repeat`]).
+    pub fn drain_events<F>(&mut self, mut handler: F) -> io::Result<bool>
+    where
+        F: FnMut(Event),
+    {
+        self.expire_toast();
+        let mut handled = false;
+        while let Some(event) = self.next_event(Duration::ZERO)? {
+            let wanted = match &event {
+                Event::Key(key) => match &mut self.key_repeat {
+                    Some(repeat) => repeat.observe(key, Instant::now()),
+                    None => key.kind == KeyEventKind::Press,
+                },
+                Event::Mouse(mouse) => {
+                    self.mouse_position = Some((mouse.column, mouse.row));
+                    true
+                }
+                Event::FocusGained | Event::FocusLost => {
+                    self.focused = matches!(event, Event::FocusGained);
+                    true
+                }
+                _ => true,
+            };
+            if wanted {
+                handler(event);
+                handled = true;
+            }
+        }
+
+        if let Some(key) = self.key_repeat.as_mut().and_then(|repeat| repeat.due(Instant::now())) {
+            handler(Event::Key(key));
+            handled = true;
+        }
+        Ok(handled)
+    }
+
+    /// Returns `true` if an event is waiting, without reading it.
+    ///
+    /// Useful for loops that animate until a key is pressed.
+    pub fn has_input(&self) -> io::Result<bool> {
+        Ok(!self.pending_events.is_empty() || event::poll(Duration::ZERO)?)
+    }
+
+    /// Returns the next event, waiting up to `timeout` for one.
+    fn next_event(&mut self, timeout: Duration) -> io::Result<Option<Event>> {
+        if let Some(event) = self.pending_events.pop_front() {
+            return Ok(Some(event));
+        }
+        Ok(if event::poll(timeout)? { Some(event::read()?) } else { None })
+    }
+
+    /// Registers the key handler called by [`Terminal::run`], replacing any earlier one.
+    pub fn set_key_handler<F>(&mut self, handler: F) -> &mut Self
+    where
+        F: FnMut(&mut Terminal, KeyEvent) + Send + 'static,
+    {
+        self.key_handler = Some(Box::new(handler));
+        self
+    }
+
+    /// Makes [`Terminal::run`] return once the current iteration finishes.
+    pub fn request_quit(&mut self) -> &mut Self {
+        self.quit_requested = true;
+        self
+    }
+
+    /// Runs a simple event loop at `fps` frames per second until `request_quit` is called.
+    ///
+    /// Each iteration passes pending key presses to the handler from
+    /// `set_key_handler`, then draws with `draw` inside a [`Terminal::frame`]
+    /// (skipped while unfocused with `set_pause_on_blur`).
+    /// Headless terminals skip input polling.
+    ///
+    /// ```rust
+    /// let mut term = blessingrs::Terminal::headless();
+    /// term.set_key_handler(|term, key| {
+    ///     if key.code == crossterm::event::KeyCode::Char('q') {
+    ///         term.request_quit();
+    ///     }
+    /// });
+    /// let mut frames = 0;
+    /// term.run(1000, |term| {
+    ///     frames += 1;
+    ///     term.move_to(0, 0).print(frames);
+    ///     if frames == 3 {
+    ///         term.request_quit();
+    ///     }
+    /// }).unwrap();
+    /// assert_eq!(frames, 3);
+    /// ```
+    pub fn run<F>(&mut self, fps: u32, mut draw: F) -> io::Result<()>
+    where
+        F: FnMut(&mut Terminal),
+    {
+        self.quit_requested = false;
+        let mut limiter = FrameLimiter::new(fps);
+        while !self.quit_requested {
+            let mut keys = Vec::new();
+            if !self.headless {
+                self.drain_events(|event| {
+                    if let Event::Key(key) = event {
+                        keys.push(key);
+                    }
+                })?;
+            } else {
+                self.expire_toast();
+            }
+            if let Some(mut handler) = self.key_handler.take() {
+                for key in keys {
+                    handler(self, key);
+                }
+                self.key_handler.get_or_insert(handler);
+            }
+            if self.quit_requested {
+                break;
+            }
+
+            if !(self.pause_on_blur && self.focus_events && !self.focused) {
+                self.frame(&mut draw);
+            }
+            limiter.tick_blocking();
+        }
+        Ok(())
+    }
+
+    /// Repeats a held key every `rate` once it's been held for `delay`, in `drain_events`.
+    ///
+    /// This smooths over terminals' own uneven key repeat, e.g. for movement in
+    /// games. Release events are used when the terminal sends them; otherwise
+    /// a key counts as released once the terminal stops repeating it. While a
+    /// key is held, the terminal's own repeats of it are swallowed.
+    pub fn set_key_repeat(&mut self, delay: Duration, rate: Duration) -> &mut Self {
+        let rate = rate.max(Duration::from_millis(1));
+        self.key_repeat = Some(KeyRepeat { delay, rate, held: None });
+        self
+    }
+
+    /// Turns software key repeat back off.
+    pub fn disable_key_repeat(&mut self) -> &mut Self {
+        self.key_repeat = None;
+        self
+    }
+
+    /// Returns the 0-based cell of the last mouse event, in the same coordinates as `move_to`.
+    pub fn mouse_position(&self) -> Option<(u16, u16)> {
+        self.mouse_position
+    }
+
+    /// Formats text based on a style string: `[attr_]...foreground[_on_background]`
+    ///
+    /// See [`Style::from_spec`] for the full grammar. Panics on an invalid spec.
+    ///
+    /// ```rust
+    /// use blessingrs::{Attrs, Color, Terminal};
+    /// let term = Terminal::headless();
+    /// assert_eq!(term.style("auto_on_yellow", "hi"), term.style("black_on_yellow", "hi"));
+    /// assert_eq!(term.style("red,bold", "hi"), term.style("bold_red", "hi"));
+    /// assert_eq!(term.style("bold_red+underline", "hi"), term.style("bold_underline_red", "hi"));
+    /// assert_eq!(term.style("on_blue", "hi"), term.style_with(None, Some(Color::Blue), Attrs::default(), "hi"));
+    /// assert_eq!(term.style("reset_red", "hi"), format!("\x1b[0m{}", term.style("red", "hi")));
+    ///
+    /// // Each styled piece closes itself, so concatenating never leaks attributes.
+    /// let warning = term.style("bold_underline_yellow_on_red", "!");
+    /// let joined = format!("{}{}", warning, "plain");
+    /// assert!(warning.ends_with("\x1b[0m"));
+    /// assert!(joined.ends_with("\x1b[0mplain"));
+    /// ```
+    pub fn style(&self, style_spec: &str, text: impl std::fmt::Display) -> String {
+        self.apply_style(&compile_spec(style_spec), &text.to_string())
+    }
+
+    /// Colors each grapheme of `text` with the next palette entry, offset by `phase`.
+    ///
+    /// Incrementing `phase` every frame makes the colors march along the text
+    /// like a marquee; it wraps around the palette's length.
+    ///
+    /// ```rust
+    /// use blessingrs::{Color, Terminal};
+    /// let term = Terminal::headless();
+    /// let palette = [Color::Red, Color::Blue];
+    /// assert_eq!(term.cycle_colors("ab", &palette, 1), "\x1b[38;5;12ma\x1b[38;5;9mb\x1b[0m");
+    /// assert_eq!(term.cycle_colors("ab", &palette, 3), term.cycle_colors("ab", &palette, 1));
+    /// ```
+    pub fn cycle_colors(&self, text: &str, palette: &[Color], phase: usize) -> String {
+        if palette.is_empty() {
+            return text.to_string();
+        }
+        self.color_graphemes(text, |i| palette[(i + phase) % palette.len()])
+    }
+
+    /// Gives each grapheme of `text` the foreground `color_at(index)`, ending in a reset.
+    fn color_graphemes(&self, text: &str, mut color_at: impl FnMut(usize) -> Color) -> String {
+        if !self.colors_enabled() || text.is_empty() {
+            return text.to_string();
+        }
+
+        let mut out = String::new();
+        let mut current = None;
+        for (i, grapheme) in text.graphemes(true).enumerate() {
+            let color = color_at(i);
+            if current != Some(color) {
+                out.push_str(&style::SetForegroundColor(color).to_string());
+                current = Some(color);
+            }
+            out.push_str(grapheme);
+        }
+        out.push_str(&style::ResetColor.to_string());
+        out
+    }
+
+    /// Emits every attribute even where the terminal is thought not to support it.
+    pub fn force_all_attributes(&mut self, force: bool) -> &mut Self {
+        self.force_all_attributes = force;
+        self
+    }
+
+    /// Formats text with a compiled [`Style`].
+    pub fn apply_style(&self, style: &Style, text: &str) -> String {
+        let styled = self.style_with(style.fg, style.bg, style.attrs, text);
+        if style.reset && self.caps.ansi {
+            format!("{}{}", style::ResetColor, styled)
+        } else {
+            styled
+        }
+    }
+
+    /// Formats text with already-resolved colors and attributes.
+    ///
+    /// This is what `style` uses under the hood, for callers that compute
+    /// colors programmatically instead of writing spec strings. Styled output
+    /// always ends in a full reset, so it never bleeds into what follows.
+    ///
+    /// Italic, blink and strikethrough are silently dropped on terminals known
+    /// to render them as garbage, unless `force_all_attributes` is on.
+    pub fn style_with(&self, fg: Option<Color>, bg: Option<Color>, attrs: Attrs, text: &str) -> String {
+        let mut styled = self.sgr(fg, bg, attrs);
+        if styled.is_empty() || !self.caps.ansi {
+            return text.to_string();
+        }
+        styled.push_str(text);
+        styled.push_str(&style::ResetColor.to_string());
+        styled
+    }
+
+    /// Builds the escapes selecting `fg`, `bg` and `attrs`, minus unsupported attributes.
+    fn sgr(&self, fg: Option<Color>, bg: Option<Color>, attrs: Attrs) -> String {
+        let mut styled = String::new();
+        let colors = self.colors_enabled();
+        let _ = write_sgr(&mut styled, fg.filter(|_| colors), bg.filter(|_| colors), self.supported_attrs(attrs));
+        styled
+    }
+
+    /// Returns `attrs` minus those this terminal can't render, unless `force_all_attributes` is on.
+    fn supported_attrs(&self, mut attrs: Attrs) -> Attrs {
+        if !self.force_all_attributes {
+            attrs.set(Attrs::ITALIC, attrs.contains(Attrs::ITALIC) && self.caps.italic);
+            attrs.set(Attrs::BLINK, attrs.contains(Attrs::BLINK) && self.caps.blink);
+            attrs.set(Attrs::STRIKETHROUGH, attrs.contains(Attrs::STRIKETHROUGH) && self.caps.strikethrough);
+            if !self.caps.styled_underline && attrs.intersects(Attrs::UNDERLINE_STYLES) {
+                attrs.remove(Attrs::UNDERLINE_STYLES);
+                attrs.insert(Attrs::UNDERLINE);
+            }
+        }
+        attrs
+    }
+
+    /// Sets colors that stay active for everything printed afterwards.
+    ///
+    /// `None` leaves that color as it is. Unlike per-string styling this
+    /// doesn't reset afterwards, so call `reset_style` once the region is
+    /// done. Strings from `style` end in a full reset, which also clears
+    /// colors set here.
+    ///
+    /// ```rust
+    /// use blessingrs::{Color, Terminal};
+    /// let mut term = Terminal::headless();
+    /// term.set_colors(Some(Color::Red), None).print("a").print("b").reset_style();
+    /// assert_eq!(term.take_output(), b"\x1b[38;5;9mab\x1b[0m");
+    /// ```
+    pub fn set_colors(&mut self, fg: Option<Color>, bg: Option<Color>) -> &mut Self {
+        self.emit(DrawOp::SetColors(fg, bg)).unwrap();
+        self
+    }
+
+    /// Turns on `attrs` for everything printed afterwards, like `set_colors`.
+    pub fn set_attrs(&mut self, attrs: Attrs) -> &mut Self {
+        self.emit(DrawOp::SetAttrs(attrs)).unwrap();
+        self
+    }
+
+    pub fn move_to(&mut self, x: u16, y: u16) -> &mut Self {
+        self.try_move_to(x, y).unwrap()
+    }
+
+    /// Moves to a fraction of the current size, e.g. `(0.5, 0.5)` for the center.
+    ///
+    /// Fractions are clamped to `0.0..=1.0`, where `1.0` is the last column or row.
+    ///
+    /// ```rust
+    /// use blessingrs::{DrawOp, Terminal};
+    /// let mut term = Terminal::headless();
+    /// term.start_capture(false);
+    /// term.move_to_pct(0.5, 2.0);
+    /// assert_eq!(term.take_ops(), vec![DrawOp::MoveTo(40, 23)]);
+    /// ```
+    pub fn move_to_pct(&mut self, x_frac: f32, y_frac: f32) -> &mut Self {
+        let size = self.size();
+        let scale = |frac: f32, len: u16| (frac.clamp(0.0, 1.0) * len.saturating_sub(1) as f32).round() as u16;
+        self.move_to(scale(x_frac, size.x), scale(y_frac, size.y))
+    }
+
+    /// Like `move_to`, taking a [`Position`].
+    pub fn move_to_pos(&mut self, pos: Position) -> &mut Self {
+        self.move_to(pos.x, pos.y)
+    }
+
+    /// Like `move_to`, but returns write errors instead of panicking.
+    pub fn try_move_to(&mut self, x: u16, y: u16) -> io::Result<&mut Self> {
+        self.emit(DrawOp::MoveTo(x, y))?;
+        Ok(self)
+    }
+
+    /// Clears the screen to the terminal's default colors.
+    ///
+    /// Styling is reset first, so a background left active by earlier output
+    /// doesn't paint the cleared screen:
+    ///
+    /// ```rust
+    /// use blessingrs::{Color, Terminal};
+    /// let mut term = Terminal::headless();
+    /// term.set_colors(None, Some(Color::Blue)).take_output();
+    /// term.clear();
+    /// assert_eq!(term.take_output(), b"\x1b[0m\x1b[2J");
+    /// ```
+    pub fn clear(&mut self) -> &mut Self {
+        self.try_clear().unwrap()
+    }
+
+    /// Like `clear`, but returns write errors instead of panicking.
+    pub fn try_clear(&mut self) -> io::Result<&mut Self> {
+        self.emit(DrawOp::Clear)?;
+        Ok(self)
+    }
+
+    /// Fills the whole screen with spaces in the style's background, e.g. `clear_with("on_blue")`.
+    ///
+    /// Unlike `clear`, this gives a colored backdrop regardless of how the
+    /// terminal treats the background during an erase.
+    pub fn clear_with(&mut self, style_spec: &str) -> &mut Self {
+        let size = self.size();
+        let style = compile_spec(style_spec);
+        let row = " ".repeat(size.x as usize);
+        for y in 0..size.y {
+            self.move_to(0, y).print_styled(&style, &row);
+        }
+        self.move_to(0, 0)
+    }
+
+    /// Prints anything `Display`, such as a `&str` or a number.
+    ///
+    /// ```rust
+    /// let mut term = blessingrs::Terminal::headless();
+    /// term.print("score: ").print(42);
+    /// assert_eq!(term.take_output(), b"score: 42");
+    /// ```
+    pub fn print(&mut self, text: impl std::fmt::Display) -> &mut Self {
+        self.try_print(text).unwrap()
+    }
+
+    /// Moves to `x, y` and prints `text`.
+    ///
+    /// ```rust
+    /// let mut term = blessingrs::Terminal::headless();
+    /// term.print_at(2, 1, "a").print("b");
+    /// assert_eq!(term.take_output(), b"\x1b[2;3Hab");
+    /// ```
+    pub fn print_at(&mut self, x: u16, y: u16, text: impl std::fmt::Display) -> &mut Self {
+        self.move_to(x, y).print(text)
+    }
+
+    /// Like `print_at`, taking a [`Position`].
+    ///
+    /// ```rust
+    /// use blessingrs::{Position, Terminal};
+    /// let mut term = Terminal::headless();
+    /// let label = Position::new(2, 1);
+    /// term.print_at_pos(label, "a").move_to_pos(label.offset(0, 1)).print("b");
+    /// assert_eq!(term.take_output(), b"\x1b[2;3Ha\x1b[3;3Hb");
+    /// ```
+    pub fn print_at_pos(&mut self, pos: Position, text: impl std::fmt::Display) -> &mut Self {
+        self.print_at(pos.x, pos.y, text)
+    }
+
+    /// Prints `text` formatted with `style_spec` (see `style`).
+    pub fn style_print(&mut self, style_spec: &str, text: impl std::fmt::Display) -> &mut Self {
+        self.print_styled(&compile_spec(style_spec), &text.to_string())
+    }
+
+    /// Prints `text` formatted with a compiled [`Style`].
+    pub fn print_styled(&mut self, style: &Style, text: &str) -> &mut Self {
+        self.emit(DrawOp::Print(Some(*style), text.to_string())).unwrap();
+        self
+    }
+
+    /// Moves to `x, y` and prints `text` formatted with `style_spec`.
+    pub fn style_print_at(&mut self, x: u16, y: u16, style_spec: &str, text: impl std::fmt::Display) -> &mut Self {
+        self.move_to(x, y).style_print(style_spec, text)
+    }
+
+    /// Writes a full attribute reset (`\x1b[0m`) so following output starts from a clean slate.
+    pub fn reset_style(&mut self) -> &mut Self {
+        self.emit(DrawOp::ResetStyle).unwrap();
+        self
+    }
+
+    /// Moves the real cursor to `x, y` and makes it visible, e.g. at a text field's edit point.
+    ///
+    /// The cursor stays visible until `hide_cursor` is called.
+    pub fn place_cursor_at(&mut self, x: u16, y: u16) -> &mut Self {
+        self.move_to(x, y);
+        if !self.cursor_visible {
+            self.emit(DrawOp::ShowCursor).unwrap();
+        }
+        self
+    }
+
+    /// Hides the cursor again, as `new()` does at startup.
+    pub fn hide_cursor(&mut self) -> &mut Self {
+        if self.cursor_visible {
+            self.emit(DrawOp::HideCursor).unwrap();
+        }
+        self
+    }
+
+    /// Like `print`, but returns write errors (e.g. a broken pipe) instead of panicking.
+    ///
+    /// ```rust
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut term = blessingrs::Terminal::headless();
+    /// term.try_move_to(0, 0)?.try_print("hi")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn try_print(&mut self, text: impl std::fmt::Display) -> io::Result<&mut Self> {
+        self.emit(DrawOp::Print(None, text.to_string()))?;
+        Ok(self)
+    }
+
+    /// Starts recording every drawing call as a [`DrawOp`], e.g. to assert on in tests.
+    ///
+    /// With `emit` false, the operations are only recorded and nothing is written.
+    ///
+    /// ```rust
+    /// use blessingrs::{DrawOp, Style, Terminal};
+    /// let mut term = Terminal::headless();
+    /// term.start_capture(false);
+    /// term.clear().style_print_at(1, 2, "bold_red", "hi");
+    /// assert_eq!(term.take_ops(), vec![
+    ///     DrawOp::Clear,
+    ///     DrawOp::MoveTo(1, 2),
+    ///     DrawOp::Print(Some(Style::from_spec("bold_red").unwrap()), "hi".to_string()),
+    /// ]);
+    /// assert!(term.take_output().is_empty());
+    /// ```
+    pub fn start_capture(&mut self, emit: bool) -> &mut Self {
+        self.capture = Some(Capture { ops: Vec::new(), emit });
+        self
+    }
+
+    /// Returns the operations recorded since `start_capture` (or the last call), leaving capture on.
+    pub fn take_ops(&mut self) -> Vec<DrawOp> {
+        self.capture.as_mut().map(|capture| std::mem::take(&mut capture.ops)).unwrap_or_default()
+    }
+
+    /// Stops recording and returns whatever hasn't been taken yet.
+    pub fn stop_capture(&mut self) -> Vec<DrawOp> {
+        self.capture.take().map(|capture| capture.ops).unwrap_or_default()
+    }
+
+    /// Records `op` if capturing and writes its bytes unless capture suppresses them.
+    fn emit(&mut self, op: DrawOp) -> io::Result<()> {
+        match &op {
+            DrawOp::ShowCursor => self.cursor_visible = true,
+            DrawOp::HideCursor => self.cursor_visible = false,
+            DrawOp::MoveTo(x, y) => self.cursor_pos = Some((*x, *y)),
+            DrawOp::Print(_, text) => {
+                if let Some(pos) = self.cursor_pos {
+                    let size = self.cached_size();
+                    self.cursor_pos = Some(advance_cursor(pos, text, size, self.raw_mode, self.ambiguous_width));
+                }
+            }
+            _ => {}
+        }
+
+        let emit = self.capture.as_ref().is_none_or(|capture| capture.emit);
+        if emit {
+            match &op {
+                DrawOp::MoveTo(x, y) => self.queue_command(cursor::MoveTo(*x, *y))?,
+                DrawOp::Print(None, text) => write!(self.writer, "{}", text)?,
+                DrawOp::Print(Some(style), text) => {
+                    let styled = self.apply_style(style, text);
+                    write!(self.writer, "{}", styled)?;
+                }
+                DrawOp::Clear => {
+                    self.queue_command(style::SetAttribute(Attribute::Reset))?;
+                    self.queue_command(terminal::Clear(terminal::ClearType::All))?;
+                }
+                DrawOp::SetColors(fg, bg) => {
+                    let sgr = self.sgr(*fg, *bg, Attrs::default());
+                    self.write_escape(&sgr)?;
+                }
+                DrawOp::SetAttrs(attrs) => {
+                    let sgr = self.sgr(None, None, *attrs);
+                    self.write_escape(&sgr)?;
+                }
+                DrawOp::ResetStyle => self.queue_command(style::ResetColor)?,
+                DrawOp::ShowCursor => self.queue_command(cursor::Show)?,
+                DrawOp::HideCursor => self.queue_command(cursor::Hide)?,
+            }
+        }
+
+        if let Some(capture) = &mut self.capture {
+            capture.ops.push(op);
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) {
+        let _ = self.writer.flush();
+    }
+
+    /// Starts a synchronized update: the terminal holds off rendering until `end_sync`.
+    ///
+    /// A no-op on terminals without synchronized output support.
+    pub fn begin_sync(&mut self) -> &mut Self {
+        if self.caps.sync {
+            let _ = write!(self.writer, "\x1b[?2026h");
+        }
+        self
+    }
+
+    /// Ends a synchronized update, letting the terminal render everything at once.
+    pub fn end_sync(&mut self) -> &mut Self {
+        if self.caps.sync {
+            let _ = write!(self.writer, "\x1b[?2026l");
+        }
+        self
+    }
+
+    /// Draws a whole frame with `draw` inside a synchronized update, then flushes.
+    ///
+    /// The frame is kept so [`Terminal::redraw_last`] can re-present it.
+    ///
+    /// ```rust
+    /// use blessingrs::Terminal;
+    /// let mut term = Terminal::headless();
+    /// term.frame(|t| {
+    ///     t.move_to(0, 0).print("hi");
+    /// });
+    /// assert_eq!(term.take_output(), b"\x1b[?2026h\x1b[1;1Hhi\x1b[?2026l");
+    /// ```
+    pub fn frame<F: FnOnce(&mut Self)>(&mut self, draw: F) -> &mut Self {
+        let outer = self.capture.take();
+        let emit = outer.as_ref().is_none_or(|capture| capture.emit);
+        self.capture = Some(Capture { ops: Vec::new(), emit });
+
+        self.begin_sync();
+        draw(self);
+
+        let ops = self.capture.take().map(|capture| capture.ops).unwrap_or_default();
+        if let Some(mut outer) = outer {
+            outer.ops.extend(ops.iter().cloned());
+            self.capture = Some(outer);
+        }
+        self.last_frame = ops;
+        // The toast goes on top, but isn't part of the frame it covers.
+        self.show_toast().end_sync().flush();
+        self
+    }
+
+    /// Clears the screen and redraws the last [`Terminal::frame`] clipped to the current size.
+    ///
+    /// Handy after a resize: anything now off-screen is dropped, and newly
+    /// exposed cells stay blank until the next real frame.
+    ///
+    /// ```rust
+    /// use blessingrs::{DrawOp, Terminal};
+    /// let mut term = Terminal::headless();
+    /// term.frame(|t| {
+    ///     t.move_to(0, 0).print("hello world").move_to(0, 5).print("below");
+    /// });
+    /// term.set_size_source(|| Ok((5, 3)));
+    /// term.start_capture(false);
+    /// term.redraw_last();
+    /// assert_eq!(term.take_ops(), vec![
+    ///     DrawOp::Clear,
+    ///     DrawOp::MoveTo(0, 0),
+    ///     DrawOp::Print(None, "hello".into()),
+    /// ]);
+    /// ```
+    pub fn redraw_last(&mut self) -> &mut Self {
+        let Size { x: width, y: height } = self.size();
+        let ambiguous = self.ambiguous_width;
+        let ops = self.last_frame.clone();
+
+        self.begin_sync();
+        self.emit(DrawOp::Clear).unwrap();
+        let (mut x, mut y) = (0u16, 0u16);
+        for op in ops {
+            match op {
+                DrawOp::MoveTo(to_x, to_y) => {
+                    (x, y) = (to_x, to_y);
+                    if x < width && y < height {
+                        self.emit(op).unwrap();
+                    }
+                }
+                DrawOp::Print(style, text) => {
+                    let room = width.saturating_sub(x) as usize;
+                    if y >= height || room == 0 {
+                        continue;
+                    }
+                    let (clipped, used) = clip_ansi(&text, room, ambiguous);
+                    x = x.saturating_add(used as u16);
+                    self.emit(DrawOp::Print(style, clipped)).unwrap();
+                }
+                DrawOp::Clear => {}
+                other => self.emit(other).unwrap(),
+            }
+        }
+        self.end_sync();
+        self.flush();
+        self
+    }
+
+    /// Prints `text` at `x, y`, cut off at the terminal's right edge and skipped below the bottom.
+    ///
+    /// The drawing helpers go through this, so on a terminal smaller than what
+    /// they draw, they draw what fits rather than wrapping or panicking.
+    fn print_clipped(&mut self, x: u16, y: u16, style: Option<&Style>, text: &str) -> &mut Self {
+        let size = self.size();
+        if x >= size.x || y >= size.y {
+            return self;
+        }
+        let fits = truncate_with(text, (size.x - x) as usize, "", self.ambiguous_width);
+        self.move_to(x, y).emit(DrawOp::Print(style.copied(), fits)).unwrap();
+        self
+    }
+
+    /// Draws a `w` x `h` box outline with its top-left corner at `x, y`.
+    ///
+    /// Like the other drawing helpers, anything past the edge of the terminal is clipped:
+    ///
+    /// ```rust
+    /// use blessingrs::{Align, LineEditor, ScrollView, Terminal};
+    /// let mut term = Terminal::headless();
+    /// term.set_size_source(|| Ok((1, 1)));
+    /// term.draw_box(0, 0, 20, 5, "red")
+    ///     .draw_box_titled(3, 0, 2, 2, "Title", Align::Center, "")
+    ///     .draw_menu(0, 0, &["New", "Open"], 1, "", "reverse")
+    ///     .draw_line_editor(0, 0, &LineEditor::new(), 10)
+    ///     .draw_scroll_view(0, 0, 30, 10, &ScrollView::from_text("a\nb"), "")
+    ///     .rule_titled(0, "Section", "")
+    ///     .write_styled_line(0, 0, "wide text", "on_blue")
+    ///     .fill_rect(0, 0, 10, 3, "on_red")
+    ///     .move_to_pct(1.0, 1.0);
+    /// assert_eq!(term.size().x, 1);
+    /// ```
+    pub fn draw_box(&mut self, x: u16, y: u16, w: u16, h: u16, style_spec: &str) -> &mut Self {
+        self.draw_box_titled(x, y, w, h, "", Align::Left, style_spec)
+    }
+
+    /// Draws a box outline around `rect`, resolved against the current size.
+    ///
+    /// ```rust
+    /// use blessingrs::{Rect, Terminal};
+    /// let mut term = Terminal::headless();
+    /// term.fill_rect_in(Rect::centered_pct(0.6, 0.4), "on_blue")
+    ///     .draw_box_in(Rect::centered_pct(0.6, 0.4), "white_on_blue");
+    /// ```
+    pub fn draw_box_in(&mut self, rect: Rect, style_spec: &str) -> &mut Self {
+        let (x, y, w, h) = rect.resolve(self.size());
+        self.draw_box(x, y, w, h, style_spec)
+    }
+
+    /// Paints a `w` x `h` area with spaces styled by `style_spec`, e.g. a background color.
+    pub fn fill_rect(&mut self, x: u16, y: u16, w: u16, h: u16, style_spec: &str) -> &mut Self {
+        let style = compile_spec(style_spec);
+        let blank = " ".repeat(w as usize);
+        for row in 0..h {
+            self.print_clipped(x, y.saturating_add(row), Some(&style), &blank);
+        }
+        self
+    }
+
+    /// Like `fill_rect`, with the area given as a [`Rect`].
+    pub fn fill_rect_in(&mut self, rect: Rect, style_spec: &str) -> &mut Self {
+        let (x, y, w, h) = rect.resolve(self.size());
+        self.fill_rect(x, y, w, h, style_spec)
+    }
+
+    /// Draws a box like `draw_box` with `title` embedded in the top border.
+    ///
+    /// The title is truncated to fit the border, e.g. `┌─ Menu ─────┐`.
+    /// A title with nothing visible left draws a plain border.
+    ///
+    /// ```rust
+    /// use blessingrs::{Align, DrawOp, Terminal};
+    /// let mut term = Terminal::headless();
+    /// term.start_capture(false);
+    /// term.draw_box_titled(0, 0, 3, 3, "\u{200b}", Align::Left, "");
+    /// assert!(matches!(&term.take_ops()[1], DrawOp::Print(_, top) if top == "┌─┐"));
+    /// ```
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_box_titled(
+        &mut self,
+        x: u16,
+        y: u16,
+        w: u16,
+        h: u16,
+        title: &str,
+        align: Align,
+        style_spec: &str,
+    ) -> &mut Self {
+        if w < 2 || h < 2 {
+            return self;
+        }
+        let inner = (w - 2) as usize;
+        let glyphs = self.glyphs();
+
+        let top = match self.truncate(title, inner.saturating_sub(4)) {
+            label if self.display_width(&label) == 0 => glyphs.horizontal.repeat(inner),
+            label => {
+                let spare = inner - self.display_width(&label) - 4;
+                let left = match align {
+                    Align::Left => 0,
+                    Align::Center => spare / 2,
+                    Align::Right => spare,
+                };
+                let line = glyphs.horizontal;
+                format!("{}{} {} {}{}", line.repeat(left), line, label, line, line.repeat(spare - left))
+            }
+        };
+
+        let style = compile_spec(style_spec);
+        let bottom = format!("{}{}{}", glyphs.bottom_left, glyphs.horizontal.repeat(inner), glyphs.bottom_right);
+
+        let right = x.saturating_add(w - 1);
+        self.print_clipped(x, y, Some(&style), &format!("{}{}{}", glyphs.top_left, top, glyphs.top_right));
+        for row in 1..h - 1 {
+            let y = y.saturating_add(row);
+            self.print_clipped(x, y, Some(&style), glyphs.vertical);
+            self.print_clipped(right, y, Some(&style), glyphs.vertical);
+        }
+        self.print_clipped(x, y.saturating_add(h - 1), Some(&style), &bottom)
+    }
+
+    /// Draws `text` at `x, y` and pads it with styled spaces to the right edge of the terminal.
+    ///
+    /// The background fills the whole row, so highlights span edge to edge.
+    /// Text too long for the row is truncated.
+    ///
+    /// ```rust
+    /// use blessingrs::{DrawOp, Style, Terminal};
+    /// let mut term = Terminal::headless();
+    /// term.set_size_source(|| Ok((10, 1)));
+    /// term.start_capture(false);
+    /// term.write_styled_line(2, 0, "日本", "on_blue");
+    /// assert_eq!(term.take_ops()[1], DrawOp::Print(Some(Style::from_spec("on_blue").unwrap()), "日本    ".into()));
+    /// ```
+    pub fn write_styled_line(&mut self, x: u16, y: u16, text: &str, style_spec: &str) -> &mut Self {
+        let width = self.size().x.saturating_sub(x) as usize;
+        let line = self.truncate(text, width);
+        let padding = " ".repeat(width - self.display_width(&line));
+        let style = compile_spec(style_spec);
+        self.print_clipped(x, y, Some(&style), &format!("{}{}", line, padding))
+    }
+
+    /// Prints the `width` columns of `text` starting at column `h_offset`, for horizontal scrolling.
+    ///
+    /// Styling inside `text` carries across the window's edges (see
+    /// [`slice_columns`]), and the window is padded with blanks where the
+    /// text runs out.
+    ///
+    /// ```rust
+    /// use blessingrs::{DrawOp, Terminal};
+    /// let mut term = Terminal::headless();
+    /// term.start_capture(false);
+    /// term.print_scrolled(0, 0, "a long log line", 6, 7, "")
+    ///     .print_scrolled(0, 1, "short", 4, 20, "");
+    /// let ops = term.take_ops();
+    /// assert_eq!(ops[1], DrawOp::Print(Some(Default::default()), "log li".into()));
+    /// assert_eq!(ops[3], DrawOp::Print(Some(Default::default()), "    ".into()));
+    /// ```
+    pub fn print_scrolled(
+        &mut self,
+        x: u16,
+        y: u16,
+        text: &str,
+        width: u16,
+        h_offset: usize,
+        style_spec: &str,
+    ) -> &mut Self {
+        let window = slice_columns_with(text, h_offset, width as usize, self.ambiguous_width);
+        let (_, used) = clip_ansi(&window, width as usize, self.ambiguous_width);
+        let padding = " ".repeat((width as usize).saturating_sub(used));
+        let style = compile_spec(style_spec);
+        self.print_clipped(x, y, Some(&style), &format!("{}{}", window, padding))
+    }
+
+    /// Draws the last `width` of `values` as a one-row bar chart from `x, y`, scaled to their min and max.
+    ///
+    /// Non-finite values and a series with no spread draw as the lowest bar,
+    /// and an empty series as a baseline of `width` lowest bars.
+    ///
+    /// ```rust
+    /// use blessingrs::{DrawOp, Terminal};
+    /// let mut term = Terminal::headless();
+    /// term.start_capture(false);
+    /// term.sparkline(0, 0, 4, &[9.0, 1.0, 3.0, 5.0, 8.0], "green")
+    ///     .sparkline(0, 1, 3, &[2.0, 2.0], "")
+    ///     .sparkline(0, 2, 3, &[], "");
+    /// let bars: Vec<_> = term.take_ops().into_iter().filter_map(|op| match op {
+    ///     DrawOp::Print(_, text) => Some(text),
+    ///     _ => None,
+    /// }).collect();
+    /// assert_eq!(bars, ["▁▃▅█", "▁▁", "▁▁▁"]);
+    /// ```
+    pub fn sparkline(&mut self, x: u16, y: u16, width: u16, values: &[f64], style_spec: &str) -> &mut Self {
+        let values = &values[values.len().saturating_sub(width as usize)..];
+        let sparks = self.glyphs().sparks;
+        let style = compile_spec(style_spec);
+        if values.is_empty() {
+            return self.print_clipped(x, y, Some(&style), &sparks[0].repeat(width as usize));
+        }
+
+        let finite = values.iter().copied().filter(|v| v.is_finite());
+        let min = finite.clone().fold(f64::INFINITY, f64::min);
+        let max = finite.fold(f64::NEG_INFINITY, f64::max);
+        let bars: String = values
+            .iter()
+            .map(|&v| {
+                if v.is_finite() && max > min {
+                    sparks[((v - min) / (max - min) * 7.0).round() as usize]
+                } else {
+                    sparks[0]
+                }
+            })
+            .collect();
+
+        self.print_clipped(x, y, Some(&style), &bars)
+    }
+
+    /// Prints `d` formatted by [`format_duration`] at `x, y`, e.g. an elapsed time in a header.
+    pub fn print_timer(&mut self, x: u16, y: u16, d: Duration, style_spec: &str) -> &mut Self {
+        let style = compile_spec(style_spec);
+        self.print_clipped(x, y, Some(&style), &format_duration(d))
+    }
+
+    /// Prints `text` top to bottom, one grapheme per row from `x, y`, e.g. for a chart's axis label.
+    ///
+    /// Rows past the bottom edge are clipped.
+    ///
+    /// ```rust
+    /// use blessingrs::{DrawOp, Terminal};
+    /// let mut term = Terminal::headless();
+    /// term.start_capture(false);
+    /// term.print_vertical(0, 22, "日ab", "");
+    /// assert_eq!(term.take_ops(), vec![
+    ///     DrawOp::MoveTo(0, 22),
+    ///     DrawOp::Print(Some(Default::default()), "日".into()),
+    ///     DrawOp::MoveTo(0, 23),
+    ///     DrawOp::Print(Some(Default::default()), "a".into()),
+    /// ]);
+    /// ```
+    pub fn print_vertical(&mut self, x: u16, y: u16, text: &str, style_spec: &str) -> &mut Self {
+        let style = compile_spec(style_spec);
+        let rows = self.size().y.saturating_sub(y) as usize;
+        for (i, grapheme) in text.graphemes(true).take(rows).enumerate() {
+            self.print_clipped(x, y + i as u16, Some(&style), grapheme);
+        }
+        self
+    }
+
+    /// Prints differently styled segments as one line from `x, y`, e.g. a syntax-highlighted row.
+    ///
+    /// Between segments only the changes in style are written, with a single
+    /// reset at the end, so it's much shorter than styling each segment on
+    /// its own. The line is clipped at the right edge.
+    ///
+    /// ```rust
+    /// use blessingrs::{Style, Terminal};
+    /// let mut term = Terminal::headless();
+    /// let (keyword, plain, name) = (Style::from_spec("bold_red").unwrap(), Style::from_spec("red").unwrap(), Style::from_spec("bold_blue").unwrap());
+    /// term.write_spans(0, 0, &[(keyword, "fn"), (plain, " "), (name, "main")]);
+    /// assert_eq!(term.take_output(), b"\x1b[1;1H\x1b[38;5;9m\x1b[1mfn\x1b[22m \x1b[38;5;12m\x1b[1mmain\x1b[0m");
+    /// ```
+    pub fn write_spans(&mut self, x: u16, y: u16, spans: &[(Style, &str)]) -> &mut Self {
+        let size = self.size();
+        if x >= size.x || y >= size.y {
+            return self;
+        }
+
+        let colors = self.colors_enabled();
+        let mut room = (size.x - x) as usize;
+        let mut line = String::new();
+        let mut current = Style::default();
+        for (style, text) in spans {
+            let text = truncate_with(text, room, "", self.ambiguous_width);
+            if text.is_empty() {
+                continue;
+            }
+            room -= self.display_width(&text);
+            if self.caps.ansi {
+                let next = Style {
+                    fg: style.fg.filter(|_| colors),
+                    bg: style.bg.filter(|_| colors),
+                    attrs: self.supported_attrs(style.attrs),
+                    reset: false,
+                };
+                let _ = write_sgr_delta(&mut line, &current, &next);
+                current = next;
+            }
+            line.push_str(&text);
+        }
+        if current != Style::default() {
+            line.push_str(&style::ResetColor.to_string());
+        }
+        self.move_to(x, y).emit(DrawOp::Print(None, line)).unwrap();
+        self
+    }
+
+    /// Word-wraps `text` to `width` columns and prints it from `x, y` down, returning the rows used.
+    ///
+    /// `width` is narrowed to what fits on the terminal, and rows past the
+    /// bottom are clipped (but still counted).
+    ///
+    /// ```rust
+    /// use blessingrs::{DrawOp, Terminal};
+    /// let mut term = Terminal::headless();
+    /// term.start_capture(false);
+    /// let rows = term.print_wrapped(2, 1, "the quick brown fox", 10, "");
+    /// assert_eq!(rows, 2);
+    /// assert_eq!(term.take_ops()[2], DrawOp::MoveTo(2, 2));
+    /// ```
+    pub fn print_wrapped(&mut self, x: u16, y: u16, text: &str, width: u16, style_spec: &str) -> u16 {
+        let width = width.min(self.size().x.saturating_sub(x));
+        let style = compile_spec(style_spec);
+        let lines = wrap_with(text, width as usize, self.ambiguous_width);
+        for (i, line) in lines.iter().enumerate() {
+            let row = y.saturating_add(i.min(u16::MAX as usize) as u16);
+            self.print_clipped(x, row, Some(&style), line);
+        }
+        lines.len().min(u16::MAX as usize) as u16
+    }
+
+    /// Draws `lines` as a block centered both horizontally and vertically, e.g. a splash banner.
+    ///
+    /// Lines are left-aligned within the block, which is as wide as its widest
+    /// line. A block bigger than the terminal is clipped.
+    ///
+    /// ```rust
+    /// use blessingrs::{DrawOp, Terminal};
+    /// let mut term = Terminal::headless();
+    /// term.start_capture(false);
+    /// term.draw_centered_block(&["blessingrs", "v0.1"], "bold");
+    /// let ops = term.take_ops();
+    /// assert_eq!((ops[0].clone(), ops[2].clone()), (DrawOp::MoveTo(35, 11), DrawOp::MoveTo(35, 12)));
+    /// ```
+    pub fn draw_centered_block(&mut self, lines: &[&str], style_spec: &str) -> &mut Self {
+        let size = self.size();
+        let width = lines.iter().map(|line| self.display_width(line)).max().unwrap_or(0);
+        let x = (size.x as usize).saturating_sub(width) / 2;
+        let y = (size.y as usize).saturating_sub(lines.len()) / 2;
+
+        let style = compile_spec(style_spec);
+        for (i, line) in lines.iter().enumerate() {
+            let row = (y + i).min(u16::MAX as usize) as u16;
+            self.print_clipped(x as u16, row, Some(&style), line);
+        }
+        self
+    }
+
+    /// Draws a horizontal `─` line across the full width of the terminal at `row`.
+    pub fn rule(&mut self, row: u16, style_spec: &str) -> &mut Self {
+        self.rule_titled(row, "", style_spec)
+    }
+
+    /// Draws a full-width rule with `title` centered in it, e.g. `──── Section ────`.
+    ///
+    /// ```rust
+    /// use blessingrs::Terminal;
+    /// let mut term = Terminal::headless();
+    /// term.set_size_source(|| Ok((16, 1)));
+    /// term.rule_titled(0, "Logs", "");
+    /// assert_eq!(String::from_utf8(term.take_output()).unwrap(), "\x1b[1;1H───── Logs ─────");
+    ///
+    /// // A title with no visible width leaves a plain rule.
+    /// term.set_size_source(|| Ok((1, 1)));
+    /// term.rule_titled(0, "\u{301}", "");
+    /// assert_eq!(String::from_utf8(term.take_output()).unwrap(), "\x1b[1;1H─");
+    /// ```
+    pub fn rule_titled(&mut self, row: u16, title: &str, style_spec: &str) -> &mut Self {
+        let width = self.size().x as usize;
+        let line = match self.truncate(title, width.saturating_sub(2)) {
+            label if self.display_width(&label) == 0 => self.glyphs().horizontal.repeat(width),
+            label => {
+                let spare = width.saturating_sub(self.display_width(&label) + 2);
+                let left = spare / 2;
+                let line = self.glyphs().horizontal;
+                format!("{} {} {}", line.repeat(left), label, line.repeat(spare - left))
+            }
+        };
+
+        let style = compile_spec(style_spec);
+        self.print_clipped(0, row, Some(&style), &line)
+    }
+
+    /// Draws a titled box with `body` word-wrapped inside it, e.g. for help popups.
+    ///
+    /// Text that doesn't fit is cut off, with an ellipsis on the last line
+    /// shown. A box too small to hold any text just gets its border.
+    ///
+    /// ```rust
+    /// use blessingrs::{DrawOp, Terminal};
+    /// let mut term = Terminal::headless();
+    /// term.start_capture(false);
+    /// term.panel(0, 0, 12, 4, "Help", "press q to quit the program", "", "");
+    /// let text: Vec<_> = term.take_ops().into_iter().filter_map(|op| match op {
+    ///     DrawOp::Print(_, text) if !text.contains('│') && !text.contains('─') => Some(text),
+    ///     _ => None,
+    /// }).collect();
+    /// assert_eq!(text, ["press q to", "quit the…"]);
+    /// ```
+    #[allow(clippy::too_many_arguments)]
+    pub fn panel(
+        &mut self,
+        x: u16,
+        y: u16,
+        w: u16,
+        h: u16,
+        title: &str,
+        body: &str,
+        border_spec: &str,
+        text_spec: &str,
+    ) -> &mut Self {
+        self.draw_box_titled(x, y, w, h, title, Align::Left, border_spec);
+        if w < 3 || h < 3 {
+            return self;
+        }
+
+        let (inner_w, inner_h) = ((w - 2) as usize, (h - 2) as usize);
+        let mut lines = wrap_with(body, inner_w, self.ambiguous_width);
+        if lines.len() > inner_h {
+            lines.truncate(inner_h);
+            let last = lines.pop().unwrap_or_default();
+            lines.push(self.truncate(&format!("{}{}", last, self.ellipsis()), inner_w));
+        }
+
+        let style = compile_spec(text_spec);
+        for (i, line) in lines.iter().enumerate() {
+            self.print_clipped(x.saturating_add(1), y.saturating_add(1 + i as u16), Some(&style), line);
+        }
+        self
+    }
+
+    /// Draws `items` as a vertical list starting at `x, y`, highlighting `selected`.
+    ///
+    /// Every row is padded to the widest item so the highlight forms an even bar;
+    /// [`Menu::measure`] gives the resulting size up front.
+    /// Navigation is left to the caller; this only draws the current state.
+    pub fn draw_menu(
+        &mut self,
+        x: u16,
+        y: u16,
+        items: &[&str],
+        selected: usize,
+        normal_spec: &str,
+        selected_spec: &str,
+    ) -> &mut Self {
+        let width = items.iter().map(|item| self.display_width(item)).max().unwrap_or(0);
+
+        for (i, item) in items.iter().enumerate() {
+            let spec = if i == selected { selected_spec } else { normal_spec };
+            let padding = " ".repeat(width - self.display_width(item));
+            let row = y.saturating_add(i.min(u16::MAX as usize) as u16);
+            self.print_clipped(x, row, Some(&compile_spec(spec)), &format!("{}{}", item, padding));
+        }
+        self
+    }
 
-use std::io::{self, Stdout, Write, BufWriter};
-use std::time::Duration;
-use crossterm::{
-    cursor, execute, queue,
-    event::{self, Event, KeyEvent, KeyEventKind},
-    style::{self, Stylize, Color},
-    terminal,
-};
+    /// Draws `editor` in a `width`-column field at `x, y`.
+    ///
+    /// When the text is wider than the field it scrolls horizontally to keep
+    /// the cursor in view. The field is padded with spaces to its full width.
+    pub fn draw_line_editor(&mut self, x: u16, y: u16, editor: &LineEditor, width: u16) -> &mut Self {
+        let width = width as usize;
+        let cursor_col = self.display_width(&editor.buffer[..editor.cursor]);
+        let offset = (cursor_col + 1).saturating_sub(width);
 
-/// Struct for .size()
-pub struct Size {
-    x: u16,
-    y: u16,
+        let mut line = String::new();
+        let mut col = 0;
+        for grapheme in editor.buffer.graphemes(true) {
+            let w = grapheme_width(grapheme, self.ambiguous_width);
+            if col >= offset && col + w <= offset + width {
+                line.push_str(grapheme);
+            } else if col < offset && col + w > offset {
+                // A wide glyph cut by the left edge leaves a gap.
+                line.push_str(&" ".repeat(col + w - offset));
+            }
+            col += w;
+        }
+
+        let padding = width.saturating_sub(self.display_width(&line));
+        line.push_str(&" ".repeat(padding));
+        self.print_clipped(x, y, None, &line)
+    }
+
+    /// Shows `prompt` at the cursor and reads a line of input with basic editing.
+    ///
+    /// Blocks until Enter, returning the entered text, or Esc / Ctrl-C,
+    /// returning `None`. Editing follows [`LineEditor`].
+    pub fn read_line(&mut self, prompt: &str) -> io::Result<Option<String>> {
+        // This blocks on input anyway, so asking the terminal costs nothing.
+        let (x, y) = self.cursor_pos.or_else(|| self.request_cursor_report().ok()).unwrap_or((0, 0));
+        let field_x = x.saturating_add(self.display_width(prompt) as u16);
+        let mut editor = LineEditor::new();
+        let was_visible = self.cursor_visible;
+
+        self.move_to(x, y).print(prompt);
+        let result = loop {
+            let width = self.size().x.saturating_sub(field_x).max(1);
+            self.draw_line_editor(field_x, y, &editor, width);
+            let cursor_col = self.display_width(&editor.buffer[..editor.cursor]) as u16;
+            self.place_cursor_at(field_x + cursor_col.min(width - 1), y);
+            self.writer.flush()?;
+
+            let event = match self.pending_events.pop_front() {
+                Some(event) => event,
+                None => event::read()?,
+            };
+            let Event::Key(key) = event else { continue };
+            if key.kind == KeyEventKind::Release {
+                continue;
+            }
+            match key.code {
+                KeyCode::Enter => break Some(editor.buffer),
+                KeyCode::Esc => break None,
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => break None,
+                _ => {
+                    editor.handle_key(key);
+                }
+            }
+        };
+
+        if !was_visible {
+            self.hide_cursor();
+        }
+        self.writer.flush()?;
+        Ok(result)
+    }
+
+    /// Draws `canvas` with its top-left cell at `ox, oy`, two pixels per cell.
+    ///
+    /// Blank pixels show the terminal's default background. Cells past the
+    /// edge of the terminal are clipped, and nothing is drawn without ANSI support.
+    pub fn draw_half_block_canvas(&mut self, ox: u16, oy: u16, canvas: &HalfBlockCanvas) -> &mut Self {
+        if !self.colors_enabled() {
+            return self;
+        }
+        let size = self.size();
+        let cols = canvas.width.min(size.x.saturating_sub(ox));
+        let rows = canvas.height.div_ceil(2).min(size.y.saturating_sub(oy));
+
+        for row in 0..rows {
+            let mut line = String::new();
+            let mut last = (None, None);
+            for col in 0..cols {
+                let top = canvas.pixel(col, row * 2);
+                let bottom = canvas.pixel(col, row * 2 + 1);
+                // A lone bottom pixel uses the lower half block, keeping the background default.
+                let (glyph, fg, bg) = match (top, bottom) {
+                    // Without half blocks, each cell shows one of its pixels as background.
+                    (top, bottom) if self.ascii_glyphs() => (' ', last.0, top.or(bottom)),
+                    (Some(top), bottom) => ('▀', Some(top), bottom),
+                    (None, Some(bottom)) => ('▄', Some(bottom), None),
+                    (None, None) => (' ', last.0, None),
+                };
+
+                if fg != last.0
+                    && let Some(fg) = fg
+                {
+                    line.push_str(&style::SetForegroundColor(fg).to_string());
+                }
+                if bg != last.1 {
+                    line.push_str(&style::SetBackgroundColor(bg.unwrap_or(Color::Reset)).to_string());
+                }
+                last = (fg, bg);
+                line.push(glyph);
+            }
+            line.push_str(&style::ResetColor.to_string());
+            self.move_to(ox, oy + row).print(&line);
+        }
+        self
+    }
+
+    /// Draws the visible part of `view` in the `w` x `h` area at `x, y`.
+    ///
+    /// Lines are wrapped to the area's width. When the content doesn't fit,
+    /// the right-hand column shows a scrollbar, ending in an overflow indicator
+    /// while there's more below (see `set_overflow_indicator`).
+    ///
+    /// ```rust
+    /// use blessingrs::{DrawOp, ScrollView, Terminal};
+    /// let mut term = Terminal::headless();
+    /// term.set_overflow_indicator("+").start_capture(false);
+    /// term.draw_scroll_view(0, 0, 5, 2, &ScrollView::from_text("a\nb\nc"), "");
+    /// let bar: Vec<_> = term.take_ops().into_iter().skip(5).step_by(2).collect();
+    /// assert_eq!(bar, [DrawOp::Print(Some(Default::default()), "█".into()), DrawOp::Print(Some(Default::default()), "+".into())]);
+    /// ```
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_scroll_view(
+        &mut self,
+        x: u16,
+        y: u16,
+        w: u16,
+        h: u16,
+        view: &ScrollView,
+        style_spec: &str,
+    ) -> &mut Self {
+        if w == 0 || h == 0 {
+            return self;
+        }
+        let style = compile_spec(style_spec);
+        let ambiguous = self.ambiguous_width;
+        let rows_at = |width: u16| {
+            view.lines[view.offset..]
+                .iter()
+                .flat_map(|line| wrap_with(line, width as usize, ambiguous))
+                .take(h as usize + 1)
+                .collect::<Vec<_>>()
+        };
+
+        let mut rows = rows_at(w);
+        let scrollbar = w > 1 && (view.offset > 0 || rows.len() > h as usize);
+        let text_w = if scrollbar { w - 1 } else { w };
+        if scrollbar {
+            rows = rows_at(text_w);
+        }
+
+        for row in 0..h {
+            let line = rows.get(row as usize).map_or("", String::as_str);
+            let padding = " ".repeat((text_w as usize).saturating_sub(self.display_width(line)));
+            self.print_clipped(x, y.saturating_add(row), Some(&style), &format!("{}{}", line, padding));
+        }
+
+        if scrollbar {
+            let max = view.max_offset().max(1);
+            let thumb = (view.offset.min(max) * (h as usize - 1) / max) as u16;
+            let more_below = rows.len() > h as usize;
+            for row in 0..h {
+                let glyph = match row {
+                    _ if row == thumb => self.glyphs().scroll_thumb.to_string(),
+                    _ if row == h - 1 && more_below => truncate_with(self.overflow_indicator(), 1, "", ambiguous),
+                    _ => self.glyphs().vertical.to_string(),
+                };
+                self.print_clipped(x.saturating_add(text_w), y.saturating_add(row), Some(&style), &glyph);
+            }
+        }
+        self
+    }
+
+    /// Moves to `x, y` until the returned guard drops, then moves back.
+    ///
+    /// The position to go back to comes from tracking `move_to` and `print`,
+    /// so guards can nest and restore exactly without querying the terminal.
+    /// Cursor visibility is restored too. Until the first `move_to` the
+    /// position isn't known, so the guard then leaves the cursor where it is.
+    ///
+    /// ```rust
+    /// use blessingrs::Terminal;
+    /// let mut term = Terminal::headless();
+    /// term.move_to(1, 1);
+    /// {
+    ///     let mut status = term.location(10, 0);
+    ///     status.print("ready");
+    ///     {
+    ///         let mut footer = status.location(0, 23);
+    ///         footer.print("q: quit").place_cursor_at(0, 0);
+    ///     }
+    ///     assert_eq!(status.cursor_position(), Some((15, 0)));
+    /// }
+    /// assert_eq!(term.cursor_position(), Some((1, 1)));
+    ///
+    /// // Text past the right edge wraps, and the tracked position follows.
+    /// term.move_to(78, 2).print("abcd");
+    /// assert_eq!(term.cursor_position(), Some((2, 3)));
+    /// ```
+    pub fn location(&mut self, x: u16, y: u16) -> LocationGuard<'_> {
+        let saved = self.cursor_pos;
+        let saved_visible = self.cursor_visible;
+        self.move_to(x, y);
+
+        LocationGuard {
+            term: self,
+            saved,
+            saved_visible,
+        }
+    }
+
+    /// Returns where the cursor is, as far as this terminal's own output says.
+    ///
+    /// `None` until the first `move_to` (or `request_cursor_report`), since
+    /// where the cursor started out isn't known.
+    pub fn cursor_position(&self) -> Option<(u16, u16)> {
+        self.cursor_pos
+    }
 }
 
-/// The main entry point for managing the terminal state.
-pub struct Terminal {
-    writer: BufWriter<Stdout>,
+impl Default for Terminal {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-impl Terminal {
-    /// Initializes the terminal, enables raw mode, and switches to the alternate screen.
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        while let Some(cleanup) = self.cleanups.pop() {
+            // A panicking cleanup mustn't stop the rest, or the teardown below.
+            let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| cleanup(self)));
+        }
+        #[cfg(all(unix, feature = "sigwinch"))]
+        if let Some((id, _)) = self.resize_signal.take() {
+            signal_hook::low_level::unregister(id);
+        }
+        if self.headless {
+            return;
+        }
+        self.restore_palette();
+        if self.mouse_capture {
+            queue!(self.writer, event::DisableMouseCapture).ok();
+        }
+        if self.focus_events {
+            queue!(self.writer, event::DisableFocusChange).ok();
+        }
+        if self.alternate_screen {
+            queue!(self.writer, terminal::LeaveAlternateScreen, cursor::RestorePosition).ok();
+        } else if self.caps.commands {
+            // Output stays on screen, so don't let the shell inherit our colors.
+            queue!(self.writer, style::ResetColor).ok();
+            if self.clear_on_drop {
+                queue!(self.writer, terminal::Clear(terminal::ClearType::All), cursor::MoveTo(0, 0)).ok();
+            }
+        }
+        if !self.cursor_visible && self.caps.commands {
+            queue!(self.writer, cursor::Show).ok();
+        }
+        self.writer.flush().ok();
+        if self.raw_mode {
+            terminal::disable_raw_mode().ok();
+        }
+    }
+}
+
+/// Configures how a [`Terminal`] takes over the screen.
+///
+/// ```rust,no_run
+/// use blessingrs::Terminal;
+/// // Draw inline on the main screen, leaving a clean screen behind.
+/// let term = Terminal::builder().alternate_screen(false).clear_on_drop(true).build();
+/// ```
+#[derive(Clone, Debug)]
+pub struct TerminalBuilder {
+    alternate_screen: bool,
+    raw_mode: bool,
+    clear_on_drop: bool,
+    stderr: bool,
+}
+
+impl Default for TerminalBuilder {
+    fn default() -> Self {
+        Self { alternate_screen: true, raw_mode: true, clear_on_drop: false, stderr: false }
+    }
+}
+
+impl TerminalBuilder {
     pub fn new() -> Self {
-        let mut writer = BufWriter::new(io::stdout());
-        terminal::enable_raw_mode().expect("Failed to enable raw mode");
-        execute!(writer, terminal::EnterAlternateScreen, cursor::Hide).expect("Failed to setup terminal");
-        Self { writer }
+        Self::default()
     }
-    
-    /// Returns the size of the terminal (returns a struct with .x and .y u16s).
-    pub fn size(&self) -> Size {
-        let (x, y) = terminal::size().unwrap_or((80, 24));
-        return Size {x, y}
+
+    /// Whether to switch to the alternate screen (default on).
+    pub fn alternate_screen(mut self, on: bool) -> Self {
+        self.alternate_screen = on;
+        self
     }
 
-    /// Flushes the buffer and pauses execution for `ms` milliseconds.
-    /// Requires an active Tokio runtime.
-    pub async fn sleep(&mut self, ms: u64) {
-        self.flush();
-        tokio::time::sleep(Duration::from_millis(ms)).await;
+    /// Whether to enable raw mode (default on).
+    ///
+    /// With it off, input stays line-buffered and Ctrl-C still interrupts the
+    /// program, e.g. for an alternate-screen app that reads whole lines from
+    /// stdin. Key handlers then only see input once Enter is pressed.
+    ///
+    /// ```rust,no_run
+    /// let term = blessingrs::Terminal::builder().raw_mode(false).build();
+    /// ```
+    pub fn raw_mode(mut self, on: bool) -> Self {
+        self.raw_mode = on;
+        self
     }
 
-    /// Polls for input and executes a closure if a key event occurs.
-    /// 
-    /// This is non-blocking with a tiny timeout (1ms), making it 
-    /// perfect for high-frequency game loops or animations.
-    pub fn add_input_handler<F>(&mut self, mut handler: F) -> io::Result<()>
-    where
-        F: FnMut(KeyEvent),
-    {
-        // Poll briefly to see if an event is available
-        if event::poll(Duration::from_millis(1))? {
-            if let Event::Key(key) = event::read()? {
-                // Filter for Press to avoid double-triggers on Windows
-                if key.kind == KeyEventKind::Press {
-                    handler(key);
-                }
+    /// Without the alternate screen, whether to clear the screen on drop (default off).
+    ///
+    /// Either way a non-alternate-screen terminal resets colors and attributes
+    /// on drop, so the next shell prompt doesn't inherit them.
+    pub fn clear_on_drop(mut self, on: bool) -> Self {
+        self.clear_on_drop = on;
+        self
+    }
+
+    /// Draws on stderr instead of stdout, leaving stdout free for piped data.
+    ///
+    /// For filter-style tools that show a progress UI while writing results
+    /// to stdout. Raw mode and input still go through the controlling terminal.
+    ///
+    /// ```rust,no_run
+    /// let term = blessingrs::Terminal::builder().alternate_screen(false).stderr().build();
+    /// ```
+    pub fn stderr(mut self) -> Self {
+        self.stderr = true;
+        self
+    }
+
+    /// Enables raw mode and enters the alternate screen if configured, and hides the cursor.
+    ///
+    /// Dropping the terminal undoes only what was turned on here.
+    pub fn build(self) -> Terminal {
+        let output = if self.stderr { Output::Stderr(io::stderr()) } else { Output::Stdout(io::stdout()) };
+        let mut term = Terminal::with_output(output, Capabilities::detect());
+        term.clear_on_drop = self.clear_on_drop;
+        if self.raw_mode {
+            terminal::enable_raw_mode().expect("Failed to enable raw mode");
+            term.raw_mode = true;
+        }
+        if term.caps.commands {
+            if self.alternate_screen {
+                execute!(term.writer, cursor::SavePosition, terminal::EnterAlternateScreen)
+                    .expect("Failed to setup terminal");
+                term.alternate_screen = true;
             }
+            term.hide_cursor().flush();
         }
-        Ok(())
+        term
     }
+}
 
-    /// Formats text based on a style string: `[bold_]foreground[_on_background]`
-    pub fn style(&self, style_spec: &str, text: &str) -> String {
-        let mut is_bold = false;
-        let mut spec = style_spec;
+/// Paces a loop to a target frame rate.
+///
+/// `tick` sleeps only for whatever is left of the current frame after the
+/// loop's own work, so animations keep a steady rate instead of drifting.
+///
+/// ```rust,no_run
+/// # fn demo(term: &mut blessingrs::Terminal) {
+/// let mut limiter = blessingrs::FrameLimiter::new(30);
+/// loop {
+///     term.clear().print("frame").flush();
+///     limiter.tick_blocking();
+/// }
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct FrameLimiter {
+    frame: Duration,
+    next: Instant,
+}
+
+impl FrameLimiter {
+    /// Creates a limiter targeting `fps` frames per second.
+    pub fn new(fps: u32) -> Self {
+        let frame = Duration::from_secs(1) / fps.max(1);
+        Self { frame, next: Instant::now() + frame }
+    }
+
+    /// Waits until the next frame boundary.
+    /// Requires an active Tokio runtime.
+    ///
+    /// ```rust,no_run
+    /// # async fn demo(term: &mut blessingrs::Terminal) {
+    /// let mut limiter = blessingrs::FrameLimiter::new(30);
+    /// loop {
+    ///     term.clear().print("frame").flush();
+    ///     limiter.tick().await;
+    /// }
+    /// # }
+    /// ```
+    #[cfg(feature = "async")]
+    pub async fn tick(&mut self) {
+        if let Some(deadline) = self.advance() {
+            tokio::time::sleep_until(deadline.into()).await;
+        }
+    }
 
-        if spec.starts_with("bold_") {
-            is_bold = true;
-            spec = &spec[5..];
+    /// Like `tick`, but blocks the current thread instead of awaiting.
+    pub fn tick_blocking(&mut self) {
+        if let Some(deadline) = self.advance() {
+            std::thread::sleep(deadline.saturating_duration_since(Instant::now()));
         }
+    }
 
-        let parts: Vec<&str> = spec.split("_on_").collect();
+    /// Moves on to the next frame, returning when the current one ends if that's still ahead.
+    fn advance(&mut self) -> Option<Instant> {
+        let now = Instant::now();
+        if now < self.next {
+            let deadline = self.next;
+            self.next += self.frame;
+            Some(deadline)
+        } else {
+            // Running behind: start fresh rather than rushing to catch up.
+            self.next = now + self.frame;
+            None
+        }
+    }
+}
+
+/// Layout helpers for [`Terminal::draw_menu`].
+pub struct Menu;
+
+impl Menu {
+    /// Returns the space `draw_menu` would fill for `items`: the widest item by the item count.
+    ///
+    /// ```rust
+    /// use blessingrs::{Menu, Size};
+    /// assert_eq!(Menu::measure(&["Open", "Save as", "Quit"]), Size { x: 7, y: 3 });
+    /// ```
+    pub fn measure(items: &[&str]) -> Size {
+        let width = items.iter().map(|item| display_width(item)).max().unwrap_or(0);
+        Size {
+            x: width.min(u16::MAX as usize) as u16,
+            y: items.len().min(u16::MAX as usize) as u16,
+        }
+    }
+}
+
+/// A single-line text buffer with a cursor, for prompts and search boxes.
+///
+/// Feed it key events with `handle_key` and draw it with
+/// [`Terminal::draw_line_editor`]. The cursor always sits on a grapheme
+/// boundary, so arrows and backspace step over whole characters.
+///
+/// ```rust
+/// use blessingrs::LineEditor;
+/// use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+/// let mut editor = LineEditor::new();
+/// for key in [KeyCode::Char('n'), KeyCode::Char('o'), KeyCode::Left, KeyCode::Char('e'), KeyCode::Backspace] {
+///     editor.handle_key(KeyEvent::new(key, KeyModifiers::NONE));
+/// }
+/// assert_eq!(editor.text(), "no");
+/// assert_eq!(editor.cursor(), 1);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LineEditor {
+    buffer: String,
+    cursor: usize,
+}
 
-        let styled = match parts.as_slice() {
-            [fg_name] => {
-                let fg = self.parse_color(fg_name);
-                text.with(fg)
+impl LineEditor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the current contents.
+    pub fn text(&self) -> &str {
+        &self.buffer
+    }
+
+    /// Returns the cursor position as a byte offset into `text()`.
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Replaces the contents and moves the cursor to the end.
+    pub fn set_text(&mut self, text: &str) {
+        self.buffer = text.to_string();
+        self.cursor = self.buffer.len();
+    }
+
+    /// Empties the buffer.
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+        self.cursor = 0;
+    }
+
+    /// Applies an editing key, returning `true` if the key was used.
+    ///
+    /// Handles printable characters, backspace, delete, left/right and home/end.
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Char(c) if !key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) => {
+                self.buffer.insert(self.cursor, c);
+                self.cursor += c.len_utf8();
+            }
+            KeyCode::Backspace => {
+                let prev = self.prev_boundary();
+                self.buffer.replace_range(prev..self.cursor, "");
+                self.cursor = prev;
             }
-            [fg_name, bg_name] => {
-                let fg = self.parse_color(fg_name);
-                let bg = self.parse_color(bg_name);
-                text.with(fg).on(bg)
+            KeyCode::Delete => {
+                let next = self.next_boundary();
+                self.buffer.replace_range(self.cursor..next, "");
             }
-            _ => panic!("Invalid style format: {}. Use '[bold_]fg_on_bg'.", style_spec),
+            KeyCode::Left => self.cursor = self.prev_boundary(),
+            KeyCode::Right => self.cursor = self.next_boundary(),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.buffer.len(),
+            _ => return false,
+        }
+        true
+    }
+
+    fn prev_boundary(&self) -> usize {
+        self.buffer[..self.cursor]
+            .grapheme_indices(true)
+            .next_back()
+            .map_or(0, |(i, _)| i)
+    }
+
+    fn next_boundary(&self) -> usize {
+        self.buffer[self.cursor..]
+            .graphemes(true)
+            .next()
+            .map_or(self.cursor, |g| self.cursor + g.len())
+    }
+}
+
+/// Scrollable lines of text, such as a log, for [`Terminal::draw_scroll_view`].
+///
+/// The offset counts content lines, is changed with `handle_key`, and is
+/// always clamped so the last page stays full.
+///
+/// ```rust
+/// use blessingrs::ScrollView;
+/// use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+/// let mut view = ScrollView::new((0..100).map(|i| format!("line {}", i)).collect());
+/// view.set_page_size(20);
+/// view.handle_key(KeyEvent::new(KeyCode::PageDown, KeyModifiers::NONE));
+/// assert_eq!(view.offset(), 20);
+/// view.handle_key(KeyEvent::new(KeyCode::End, KeyModifiers::NONE));
+/// assert_eq!(view.offset(), 80);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScrollView {
+    lines: Vec<String>,
+    offset: usize,
+    page: usize,
+}
+
+impl ScrollView {
+    pub fn new(lines: Vec<String>) -> Self {
+        Self { lines, offset: 0, page: 10 }
+    }
+
+    /// Builds a view from text, one content line per line of `text`.
+    pub fn from_text(text: &str) -> Self {
+        Self::new(text.lines().map(String::from).collect())
+    }
+
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    /// Appends a line, e.g. a new log entry.
+    pub fn push(&mut self, line: &str) {
+        self.lines.push(line.to_string());
+    }
+
+    /// Returns the first visible content line.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn set_offset(&mut self, offset: usize) {
+        self.offset = offset.min(self.max_offset());
+    }
+
+    /// Sets how far PageUp/PageDown move; match it to the drawn height (default 10).
+    pub fn set_page_size(&mut self, page: usize) {
+        self.page = page.max(1);
+        self.set_offset(self.offset);
+    }
+
+    fn max_offset(&self) -> usize {
+        self.lines.len().saturating_sub(self.page)
+    }
+
+    /// Scrolls for Up/Down/PageUp/PageDown/Home/End, returning `true` if the key was used.
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        let offset = match key.code {
+            KeyCode::Up => self.offset.saturating_sub(1),
+            KeyCode::Down => self.offset + 1,
+            KeyCode::PageUp => self.offset.saturating_sub(self.page),
+            KeyCode::PageDown => self.offset + self.page,
+            KeyCode::Home => 0,
+            KeyCode::End => self.max_offset(),
+            _ => return false,
         };
+        self.set_offset(offset);
+        true
+    }
+}
 
-        if is_bold {
-            format!("{}", styled.bold())
-        } else {
-            format!("{}", styled)
+/// A message shown by [`Terminal::toast`] until `expires`.
+struct Toast {
+    x: u16,
+    y: u16,
+    width: u16,
+    label: String,
+    style: Style,
+    expires: Instant,
+}
+
+/// Software auto-repeat for a held key, configured by [`Terminal::set_key_repeat`].
+struct KeyRepeat {
+    delay: Duration,
+    rate: Duration,
+    held: Option<HeldKey>,
+}
+
+struct HeldKey {
+    key: KeyEvent,
+    last_seen: Instant,
+    next: Instant,
+    /// Whether the terminal's own repeats have started arriving.
+    os_repeating: bool,
+}
+
+impl KeyRepeat {
+    /// Without release events, a key counts as released once the terminal
+    /// stops repeating it: terminals repeat far faster than `REPEAT_GAP`, and
+    /// start repeating within `INITIAL_GAP` of the first press.
+    const REPEAT_GAP: Duration = Duration::from_millis(100);
+    const INITIAL_GAP: Duration = Duration::from_millis(600);
+
+    /// Returns whether `held` still counts as held, given how long since it was last seen.
+    fn alive(held: &HeldKey, first_gap: Duration, now: Instant) -> bool {
+        let gap = if held.os_repeating { Self::REPEAT_GAP } else { first_gap };
+        now.duration_since(held.last_seen) < gap
+    }
+
+    fn first_gap(&self) -> Duration {
+        Self::INITIAL_GAP.max(self.delay + self.rate)
+    }
+
+    /// Tracks a key event, returning whether it should reach the handler.
+    fn observe(&mut self, key: &KeyEvent, now: Instant) -> bool {
+        let same = |held: &HeldKey| held.key.code == key.code && held.key.modifiers == key.modifiers;
+        if key.kind == KeyEventKind::Release {
+            if self.held.as_ref().is_some_and(same) {
+                self.held = None;
+            }
+            return false;
+        }
+
+        let first_gap = self.first_gap();
+        if let Some(held) = &mut self.held
+            && same(held)
+            && Self::alive(held, first_gap, now)
+        {
+            // One of the terminal's own repeats; ours replace it.
+            held.last_seen = now;
+            held.os_repeating = true;
+            return false;
         }
+
+        self.held = Some(HeldKey { key: *key, last_seen: now, next: now + self.delay, os_repeating: false });
+        true
     }
 
-    fn parse_color(&self, name: &str) -> Color {
-        match name.to_lowercase().as_str() {
-            "black" => Color::Black,
-            "red" => Color::Red,
-            "green" => Color::Green,
-            "yellow" => Color::Yellow,
-            "blue" => Color::Blue,
-            "magenta" => Color::Magenta,
-            "cyan" => Color::Cyan,
-            "white" => Color::White,
-            "grey" | "gray" => Color::Grey,
-            _ => panic!("Unknown color member: {}", name),
+    /// Returns a synthesized repeat if one is due.
+    fn due(&mut self, now: Instant) -> Option<KeyEvent> {
+        let first_gap = self.first_gap();
+        let held = self.held.as_mut()?;
+        if !Self::alive(held, first_gap, now) {
+            self.held = None;
+            return None;
         }
+        if held.next > now {
+            return None;
+        }
+        held.next = now + self.rate;
+        Some(KeyEvent { kind: KeyEventKind::Repeat, ..held.key })
     }
+}
 
-    pub fn move_to(&mut self, x: u16, y: u16) -> &mut Self {
-        queue!(self.writer, cursor::MoveTo(x, y)).unwrap();
+/// A rectangular region of the screen that drawing is clipped to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Viewport {
+    pub x: u16,
+    pub y: u16,
+    pub w: u16,
+    pub h: u16,
+}
+
+impl Viewport {
+    pub fn new(x: u16, y: u16, w: u16, h: u16) -> Self {
+        Self { x, y, w, h }
+    }
+
+    /// Returns this viewport shrunk to fit on a screen of `size`.
+    fn clipped(&self, size: &Size) -> Self {
+        Self {
+            x: self.x,
+            y: self.y,
+            w: self.w.min(size.x.saturating_sub(self.x)),
+            h: self.h.min(size.y.saturating_sub(self.y)),
+        }
+    }
+
+    /// Paints the viewport with a background gradient between two `#rrggbb` colors.
+    ///
+    /// The gradient runs top to bottom when `vertical`, otherwise left to right.
+    /// Each row is emitted with a single write.
+    pub fn fill_gradient(&self, term: &mut Terminal, start_hex: &str, end_hex: &str, vertical: bool) {
+        let start = parse_hex(start_hex).unwrap_or_else(|| panic!("Invalid hex color: {}", start_hex));
+        let end = parse_hex(end_hex).unwrap_or_else(|| panic!("Invalid hex color: {}", end_hex));
+        let view = self.clipped(&term.size());
+
+        let steps = if vertical { view.h } else { view.w };
+        let color_at = |i: u16| lerp_rgb(start, end, i as f32 / steps.saturating_sub(1).max(1) as f32);
+
+        if !term.colors_enabled() {
+            return;
+        }
+
+        for row in 0..view.h {
+            let mut line = String::new();
+            let mut last = None;
+            for col in 0..view.w {
+                let color = color_at(if vertical { row } else { col });
+                if last != Some(color) {
+                    line.push_str(&format!("{}", style::SetBackgroundColor(color)));
+                    last = Some(color);
+                }
+                line.push(' ');
+            }
+            line.push_str(&format!("{}", style::SetBackgroundColor(Color::Reset)));
+            term.move_to(view.x, view.y + row).print(&line);
+        }
+    }
+}
+
+/// A pixel grid drawn at twice the vertical resolution of the terminal.
+///
+/// Each character cell shows two pixels stacked on top of each other, using
+/// the `▀` half block with the top pixel as foreground and the bottom as
+/// background. Draw it with [`Terminal::draw_half_block_canvas`].
+///
+/// ```rust
+/// use blessingrs::{Color, HalfBlockCanvas, Terminal};
+/// let mut canvas = HalfBlockCanvas::new(2, 2);
+/// canvas.set_pixel(0, 0, Color::Red);
+/// canvas.set_pixel(0, 1, Color::Blue);
+/// canvas.set_pixel(1, 1, Color::Green);
+///
+/// let mut term = Terminal::headless();
+/// term.draw_half_block_canvas(0, 0, &canvas);
+/// let out = String::from_utf8(term.take_output()).unwrap();
+/// assert_eq!(out, "\x1b[1;1H\x1b[38;5;9m\x1b[48;5;12m▀\x1b[38;5;10m\x1b[49m▄\x1b[0m");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HalfBlockCanvas {
+    width: u16,
+    height: u16,
+    pixels: Vec<Option<Color>>,
+}
+
+impl HalfBlockCanvas {
+    /// Creates a blank canvas of `width` x `height` pixels, i.e. `height / 2` rows of cells.
+    pub fn new(width: u16, height: u16) -> Self {
+        Self { width, height, pixels: vec![None; width as usize * height as usize] }
+    }
+
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    /// Colors the pixel at `x, y`; pixels outside the canvas are ignored.
+    pub fn set_pixel(&mut self, x: u16, y: u16, color: Color) {
+        if x < self.width && y < self.height {
+            self.pixels[y as usize * self.width as usize + x as usize] = Some(color);
+        }
+    }
+
+    /// Returns the color at `x, y`, or `None` if it's blank or outside the canvas.
+    pub fn pixel(&self, x: u16, y: u16) -> Option<Color> {
+        if x < self.width && y < self.height {
+            self.pixels[y as usize * self.width as usize + x as usize]
+        } else {
+            None
+        }
+    }
+
+    /// Blanks every pixel.
+    pub fn clear(&mut self) {
+        self.pixels.fill(None);
+    }
+}
+
+/// A [`Terminal`] that can be shared between threads, e.g. a logger and a UI.
+///
+/// Each method holds the lock for the whole call, so a styled write from one
+/// thread can't be torn apart by another thread's output. Only single calls
+/// are atomic; use `with` to run several calls (such as a whole frame) under
+/// one lock.
+///
+/// ```rust
+/// use blessingrs::{SharedTerminal, Terminal};
+/// let shared = SharedTerminal::new(Terminal::headless());
+/// let logger = shared.clone();
+/// std::thread::spawn(move || {
+///     logger.style_print_at(0, 23, "dim", "background job done");
+/// }).join().unwrap();
+/// shared.with(|term| {
+///     term.clear().print_at(0, 0, "frame");
+/// });
+/// ```
+#[derive(Clone)]
+pub struct SharedTerminal {
+    inner: Arc<Mutex<Terminal>>,
+}
+
+impl SharedTerminal {
+    pub fn new(term: Terminal) -> Self {
+        Self { inner: Arc::new(Mutex::new(term)) }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Terminal> {
+        // A thread that panicked mid-write can't leave the terminal in a state worth refusing.
+        self.inner.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Runs `f` with exclusive access, making everything it draws atomic.
+    pub fn with<R>(&self, f: impl FnOnce(&mut Terminal) -> R) -> R {
+        f(&mut self.lock())
+    }
+
+    pub fn move_to(&self, x: u16, y: u16) -> &Self {
+        self.lock().move_to(x, y);
         self
     }
 
-    pub fn clear(&mut self) -> &mut Self {
-        queue!(self.writer, terminal::Clear(terminal::ClearType::All)).unwrap();
+    pub fn clear(&self) -> &Self {
+        self.lock().clear();
         self
     }
 
-    pub fn print(&mut self, text: &str) -> &mut Self {
-        write!(self.writer, "{}", text).unwrap();
+    pub fn print(&self, text: impl std::fmt::Display) -> &Self {
+        self.lock().print(text);
         self
     }
 
-    pub fn flush(&mut self) {
-        let _ = self.writer.flush();
+    pub fn print_at(&self, x: u16, y: u16, text: impl std::fmt::Display) -> &Self {
+        self.lock().print_at(x, y, text);
+        self
     }
 
-    pub fn location(&mut self, x: u16, y: u16) -> LocationGuard<'_> {
-        let (saved_x, saved_y) = cursor::position().unwrap_or((0, 0));
-        queue!(self.writer, cursor::MoveTo(x, y)).unwrap();
-        
-        LocationGuard {
-            term: self,
-            saved_x,
-            saved_y,
-        }
+    pub fn style_print(&self, style_spec: &str, text: impl std::fmt::Display) -> &Self {
+        self.lock().style_print(style_spec, text);
+        self
     }
-}
 
-impl Drop for Terminal {
-    fn drop(&mut self) {
-        execute!(self.writer, terminal::LeaveAlternateScreen, cursor::Show).ok();
-        terminal::disable_raw_mode().ok();
+    pub fn style_print_at(&self, x: u16, y: u16, style_spec: &str, text: impl std::fmt::Display) -> &Self {
+        self.lock().style_print_at(x, y, style_spec, text);
+        self
+    }
+
+    pub fn flush(&self) {
+        self.lock().flush();
     }
 }
 
 /// A guard that restores cursor position when it goes out of scope.
+///
+/// It derefs to the [`Terminal`], so drawing (and nested guards) go through it.
 pub struct LocationGuard<'a> {
     term: &'a mut Terminal,
-    saved_x: u16,
-    saved_y: u16,
+    saved: Option<(u16, u16)>,
+    saved_visible: bool,
+}
+
+impl std::ops::Deref for LocationGuard<'_> {
+    type Target = Terminal;
+
+    fn deref(&self) -> &Terminal {
+        self.term
+    }
+}
+
+impl std::ops::DerefMut for LocationGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Terminal {
+        self.term
+    }
 }
 
 impl<'a> Drop for LocationGuard<'a> {
     fn drop(&mut self) {
-        queue!(self.term.writer, cursor::MoveTo(self.saved_x, self.saved_y)).unwrap();
+        if let Some((x, y)) = self.saved {
+            self.term.move_to(x, y);
+        }
+        if self.saved_visible != self.term.cursor_visible {
+            let op = if self.saved_visible { DrawOp::ShowCursor } else { DrawOp::HideCursor };
+            self.term.emit(op).ok();
+        }
     }
 }
\ No newline at end of file